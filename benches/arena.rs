@@ -0,0 +1,201 @@
+//! Compares the boxed AST with the arena AST on a synthetic feed.
+//!
+//! Run with `cargo bench --bench arena [-- <number of plugins>]`. For each
+//! representation the time to build it for the whole corpus and the heap
+//! memory that it keeps alive afterwards are reported.
+
+use nasl_transpiler::arena::Arena;
+use nasl_transpiler::ast::{self, Statement};
+use nasl_transpiler::intern::Interner;
+use nasl_transpiler::parser::Parser;
+use std::alloc::{GlobalAlloc, Layout, System};
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::time::{Duration, Instant};
+
+/// Keeps track of the number of bytes allocated on the heap.
+struct CountingAlloc;
+
+static ALLOCATED: AtomicUsize = AtomicUsize::new(0);
+
+unsafe impl GlobalAlloc for CountingAlloc {
+    unsafe fn alloc(&self, layout: Layout) -> *mut u8 {
+        ALLOCATED.fetch_add(layout.size(), Ordering::Relaxed);
+        System.alloc(layout)
+    }
+
+    unsafe fn dealloc(&self, ptr: *mut u8, layout: Layout) {
+        ALLOCATED.fetch_sub(layout.size(), Ordering::Relaxed);
+        System.dealloc(ptr, layout)
+    }
+}
+
+#[global_allocator]
+static GLOBAL: CountingAlloc = CountingAlloc;
+
+fn allocated() -> usize {
+    ALLOCATED.load(Ordering::Relaxed)
+}
+
+/// Deterministic pseudo random numbers, so that every run sees the same
+/// corpus.
+struct Rng(u64);
+
+impl Rng {
+    fn below(&mut self, n: usize) -> usize {
+        self.0 = self
+            .0
+            .wrapping_mul(6364136223846793005)
+            .wrapping_add(1442695040888963407);
+        ((self.0 >> 33) as usize) % n
+    }
+
+    fn pick<'a>(&mut self, items: &[&'a str]) -> &'a str {
+        items[self.below(items.len())]
+    }
+}
+
+const INCLUDES: &[&str] = &[
+    "http_func.inc",
+    "misc_func.inc",
+    "ssh_func.inc",
+    "smb_nt.inc",
+];
+const VARS: &[&str] = &[
+    "port", "soc", "res", "buf", "url", "banner", "data", "i", "ver",
+];
+const FUNCS: &[&str] = &["get_kb_item", "recv", "send", "http_get", "egrep", "strlen"];
+
+/// Source of a plugin that looks roughly like the ones of the feed.
+fn plugin(rng: &mut Rng, n: usize) -> String {
+    let mut code = String::new();
+    for _ in 0..1 + rng.below(3) {
+        code += &format!("include(\"{}\");\n", rng.pick(INCLUDES));
+    }
+    code += &format!(
+        "\nif (description) {{\n  script_oid(\"1.3.6.1.4.1.25623.1.0.{}\");\n  exit(0);\n}}\n\n",
+        n
+    );
+    for f in 0..2 + rng.below(4) {
+        code += &format!("function check_{}(port, data) {{\n", f);
+        code += "  local_var soc, res, i;\n";
+        code += "  soc = open_sock_tcp(port);\n  if (!soc) return NULL;\n";
+        code += "  send(socket:soc, data:data + '\\r\\n');\n";
+        for _ in 0..3 + rng.below(6) {
+            let var = rng.pick(VARS);
+            code += &match rng.below(4) {
+                0 => format!(
+                    "  {} = {}(socket:soc, length:{});\n",
+                    var,
+                    rng.pick(FUNCS),
+                    rng.below(4096)
+                ),
+                1 => format!(
+                    "  if ({} =~ \"^HTTP/1\\.[01] 200\" && {} > {}) {{\n    return {};\n  }}\n",
+                    var,
+                    rng.pick(VARS),
+                    rng.below(100),
+                    var
+                ),
+                2 => format!(
+                    "  for (i = 0; i < {}; i++) {{\n    {}[i] = ({} + i) * 2;\n  }}\n",
+                    rng.below(64),
+                    var,
+                    rng.pick(VARS)
+                ),
+                _ => format!(
+                    "  foreach {} (make_list({}, {}, \"{}\")) {{ display({}); }}\n",
+                    var,
+                    rng.below(10),
+                    rng.pick(VARS),
+                    rng.pick(INCLUDES),
+                    var
+                ),
+            };
+        }
+        code += "  close(soc);\n  return res;\n}\n\n";
+    }
+    code += "port = get_kb_item(\"Services/www\");\nif (!port) port = 80;\n";
+    code += "if (check_0(port:port, data:\"GET / HTTP/1.0\")) security_message(port:port);\n";
+    code
+}
+
+struct Measurement {
+    name: &'static str,
+    time: Duration,
+    bytes: usize,
+}
+
+fn main() {
+    let plugins = std::env::args()
+        .skip(1)
+        .find_map(|arg| arg.parse().ok())
+        .unwrap_or(20_000);
+    let mut rng = Rng(0x5eed);
+    let sources: Vec<String> = (0..plugins).map(|n| plugin(&mut rng, n)).collect();
+    let source_bytes: usize = sources.iter().map(String::len).sum();
+    println!(
+        "corpus: {} plugins, {} KiB of source",
+        plugins,
+        source_bytes / 1024
+    );
+
+    let mut results = Vec::new();
+
+    let before = allocated();
+    let start = Instant::now();
+    let boxed: Vec<Vec<Statement<&str>>> = sources
+        .iter()
+        .map(|source| Parser::new(source).parse().unwrap())
+        .collect();
+    results.push(Measurement {
+        name: "boxed, borrowing the sources",
+        time: start.elapsed(),
+        bytes: allocated() - before,
+    });
+    drop(boxed);
+
+    let before = allocated();
+    let start = Instant::now();
+    let owned: Vec<Vec<ast::OwnedStatement>> = sources
+        .iter()
+        .map(|source| ast::into_owned(Parser::new(source).parse().unwrap()))
+        .collect();
+    results.push(Measurement {
+        name: "boxed, owned strings",
+        time: start.elapsed(),
+        bytes: allocated() - before,
+    });
+    drop(owned);
+
+    let before = allocated();
+    let start = Instant::now();
+    let mut interner = Interner::new();
+    let mut arena = Arena::new();
+    for source in &sources {
+        let stmts = Parser::new(source).parse().unwrap();
+        arena.add_file(stmts, &mut interner);
+    }
+    arena.shrink_to_fit();
+    results.push(Measurement {
+        name: "arena + interner",
+        time: start.elapsed(),
+        bytes: allocated() - before,
+    });
+
+    let nodes = arena.node_count();
+    println!("{} nodes, {} distinct strings\n", nodes, interner.len());
+    println!(
+        "{:<30} {:>10} {:>12} {:>12}",
+        "representation", "time (ms)", "heap (KiB)", "bytes/node"
+    );
+    for m in &results {
+        println!(
+            "{:<30} {:>10} {:>12} {:>12.1}",
+            m.name,
+            m.time.as_millis(),
+            m.bytes / 1024,
+            m.bytes as f64 / nodes as f64
+        );
+    }
+    println!("\nThe borrowing AST also needs the sources to be kept alive.");
+}
@@ -0,0 +1,507 @@
+//! Compact representation of the AST for keeping many files in memory.
+//!
+//! The nodes of all files added to an `Arena` are stored in a handful of
+//! vectors and refer to each other by index instead of by `Box`. Strings are
+//! `Symbol`s of an `Interner`, which is usually shared by the whole feed.
+//! The nodes mirror the ones of the `ast` module, see there for what they
+//! mean.
+
+use crate::ast::{
+    self, AssignOp, BinaryOp, Expression, ExpressionKind, Ident, Statement, StatementKind, UnaryOp,
+    VarScope,
+};
+use crate::intern::{Interner, Symbol};
+use crate::span::Span;
+use std::fmt;
+use std::marker::PhantomData;
+
+/// Index of an expression in an `Arena`.
+#[derive(Debug, PartialEq, Eq, Hash, Clone, Copy)]
+pub struct ExprId(u32);
+
+/// Index of a statement in an `Arena`.
+#[derive(Debug, PartialEq, Eq, Hash, Clone, Copy)]
+pub struct StmtId(u32);
+
+/// A sequence of `T`s stored in one of the list pools of an `Arena`.
+pub struct List<T> {
+    start: u32,
+    len: u32,
+    marker: PhantomData<T>,
+}
+
+impl<T> List<T> {
+    pub fn len(&self) -> usize {
+        self.len as usize
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.len == 0
+    }
+
+    fn range(&self) -> std::ops::Range<usize> {
+        self.start as usize..(self.start + self.len) as usize
+    }
+}
+
+// Derived impls would require `T` to implement the traits as well.
+impl<T> Clone for List<T> {
+    fn clone(&self) -> Self {
+        *self
+    }
+}
+
+impl<T> Copy for List<T> {}
+
+impl<T> PartialEq for List<T> {
+    fn eq(&self, other: &Self) -> bool {
+        self.start == other.start && self.len == other.len
+    }
+}
+
+impl<T> fmt::Debug for List<T> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "List({}..{})", self.start, self.start + self.len)
+    }
+}
+
+/// A node together with its location in the source.
+#[derive(Debug, PartialEq)]
+pub struct Node<K> {
+    pub kind: K,
+    pub span: Span,
+}
+
+impl<K> Node<K> {
+    pub fn span(&self) -> Span {
+        self.span
+    }
+}
+
+/// A single variable of a declaration.
+#[derive(Debug, PartialEq, Clone, Copy)]
+pub struct VarDecl {
+    pub ident: Ident<Symbol>,
+    pub init: Option<ExprId>,
+}
+
+/// An argument of a call.
+#[derive(Debug, PartialEq, Clone, Copy)]
+pub enum Arg {
+    Anon(ExprId),
+    Named(Ident<Symbol>, ExprId),
+}
+
+#[derive(Debug, PartialEq)]
+pub enum ExprKind {
+    Identifier(Symbol),
+    Number(i64),
+    String(Symbol),
+    Data(Symbol),
+    Boolean(bool),
+    Null,
+    Prefix {
+        op: UnaryOp,
+        expr: ExprId,
+    },
+    Infix {
+        l_expr: ExprId,
+        op: BinaryOp,
+        r_expr: ExprId,
+    },
+    Postfix {
+        expr: ExprId,
+        op: UnaryOp,
+    },
+    Index {
+        expr: ExprId,
+        index: ExprId,
+    },
+    Grouped(ExprId),
+    Sequence(List<ExprId>),
+    FunctionRef(Ident<Symbol>),
+    FunctionCall {
+        ident: Ident<Symbol>,
+        args: List<Arg>,
+    },
+    Assign {
+        target: ExprId,
+        op: AssignOp,
+        expr: ExprId,
+    },
+}
+
+#[derive(Debug, PartialEq)]
+pub enum StmtKind {
+    If {
+        cond: ExprId,
+        if_block: StmtId,
+        else_block: Option<StmtId>,
+    },
+    While {
+        cond: ExprId,
+        block: StmtId,
+    },
+    For {
+        init_expr: Option<ExprId>,
+        cond: Option<ExprId>,
+        end_expr: Option<ExprId>,
+        block: StmtId,
+    },
+    Foreach {
+        var: Ident<Symbol>,
+        array: ExprId,
+        block: StmtId,
+    },
+    Repeat {
+        block: StmtId,
+        cond: ExprId,
+    },
+    FunctionDef {
+        ident: Ident<Symbol>,
+        params: List<Ident<Symbol>>,
+        uses_anon_args: bool,
+        body: StmtId,
+    },
+    Declaration {
+        scope: VarScope,
+        vars: List<VarDecl>,
+    },
+    Include {
+        path: Symbol,
+        path_span: Span,
+    },
+    Return {
+        expr: Option<ExprId>,
+    },
+    Break,
+    Continue,
+    Empty,
+    Block {
+        statements: List<StmtId>,
+    },
+    Expression(ExprId),
+    Repeated {
+        call: ExprId,
+        count: ExprId,
+    },
+}
+
+/// Storage of the nodes of any number of files.
+#[derive(Debug, Default)]
+pub struct Arena {
+    exprs: Vec<Node<ExprKind>>,
+    stmts: Vec<Node<StmtKind>>,
+    expr_lists: Vec<ExprId>,
+    stmt_lists: Vec<StmtId>,
+    idents: Vec<Ident<Symbol>>,
+    args: Vec<Arg>,
+    vars: Vec<VarDecl>,
+}
+
+impl Arena {
+    pub fn new() -> Self {
+        Arena::default()
+    }
+
+    /// Moves the statements of a parsed file into the arena and returns
+    /// the top level statements.
+    pub fn add_file(
+        &mut self,
+        stmts: Vec<Statement<&str>>,
+        interner: &mut Interner,
+    ) -> List<StmtId> {
+        let ids: Vec<_> = stmts
+            .into_iter()
+            .map(|stmt| self.add_statement(stmt, interner))
+            .collect();
+        push_list(&mut self.stmt_lists, ids)
+    }
+
+    pub fn add_statement(&mut self, stmt: Statement<&str>, interner: &mut Interner) -> StmtId {
+        let kind = match stmt.kind {
+            StatementKind::If {
+                cond,
+                if_block,
+                else_block,
+            } => StmtKind::If {
+                cond: self.add_expression(*cond, interner),
+                if_block: self.add_statement(*if_block, interner),
+                else_block: else_block.map(|block| self.add_statement(*block, interner)),
+            },
+            StatementKind::While { cond, block } => StmtKind::While {
+                cond: self.add_expression(*cond, interner),
+                block: self.add_statement(*block, interner),
+            },
+            StatementKind::For {
+                init_expr,
+                cond,
+                end_expr,
+                block,
+            } => StmtKind::For {
+                init_expr: init_expr.map(|expr| self.add_expression(*expr, interner)),
+                cond: cond.map(|expr| self.add_expression(*expr, interner)),
+                end_expr: end_expr.map(|expr| self.add_expression(*expr, interner)),
+                block: self.add_statement(*block, interner),
+            },
+            StatementKind::Foreach { var, array, block } => StmtKind::Foreach {
+                var: var.map_str(&mut |s| interner.intern(s)),
+                array: self.add_expression(*array, interner),
+                block: self.add_statement(*block, interner),
+            },
+            StatementKind::Repeat { block, cond } => StmtKind::Repeat {
+                block: self.add_statement(*block, interner),
+                cond: self.add_expression(*cond, interner),
+            },
+            StatementKind::FunctionDef {
+                ident,
+                params,
+                uses_anon_args,
+                body,
+            } => {
+                let params = params
+                    .into_iter()
+                    .map(|param| param.map_str(&mut |s| interner.intern(s)))
+                    .collect();
+                StmtKind::FunctionDef {
+                    ident: ident.map_str(&mut |s| interner.intern(s)),
+                    params: push_list(&mut self.idents, params),
+                    uses_anon_args,
+                    body: self.add_statement(*body, interner),
+                }
+            }
+            StatementKind::Declaration { scope, vars } => {
+                let vars = vars
+                    .into_iter()
+                    .map(|var| VarDecl {
+                        ident: var.ident.map_str(&mut |s| interner.intern(s)),
+                        init: var.init.map(|init| self.add_expression(*init, interner)),
+                    })
+                    .collect();
+                StmtKind::Declaration {
+                    scope,
+                    vars: push_list(&mut self.vars, vars),
+                }
+            }
+            StatementKind::Include { path, path_span } => StmtKind::Include {
+                path: interner.intern(path),
+                path_span,
+            },
+            StatementKind::Return { expr } => StmtKind::Return {
+                expr: expr.map(|expr| self.add_expression(*expr, interner)),
+            },
+            StatementKind::Break => StmtKind::Break,
+            StatementKind::Continue => StmtKind::Continue,
+            StatementKind::Empty => StmtKind::Empty,
+            StatementKind::Block { statements } => {
+                let ids = statements
+                    .into_iter()
+                    .map(|stmt| self.add_statement(stmt, interner))
+                    .collect();
+                StmtKind::Block {
+                    statements: push_list(&mut self.stmt_lists, ids),
+                }
+            }
+            StatementKind::Expression(expr) => {
+                StmtKind::Expression(self.add_expression(*expr, interner))
+            }
+            StatementKind::Repeated { call, count } => StmtKind::Repeated {
+                call: self.add_expression(*call, interner),
+                count: self.add_expression(*count, interner),
+            },
+        };
+        self.stmts.push(Node {
+            kind,
+            span: stmt.span,
+        });
+        StmtId(self.stmts.len() as u32 - 1)
+    }
+
+    pub fn add_expression(&mut self, expr: Expression<&str>, interner: &mut Interner) -> ExprId {
+        let kind = match expr.kind {
+            ExpressionKind::Identifier(name) => ExprKind::Identifier(interner.intern(name)),
+            ExpressionKind::Number(value) => ExprKind::Number(value),
+            ExpressionKind::String(value) => ExprKind::String(interner.intern(value)),
+            ExpressionKind::Data(value) => ExprKind::Data(interner.intern(value)),
+            ExpressionKind::Boolean(value) => ExprKind::Boolean(value),
+            ExpressionKind::Null => ExprKind::Null,
+            ExpressionKind::Prefix { op, expr } => ExprKind::Prefix {
+                op,
+                expr: self.add_expression(*expr, interner),
+            },
+            ExpressionKind::Infix { l_expr, op, r_expr } => ExprKind::Infix {
+                l_expr: self.add_expression(*l_expr, interner),
+                op,
+                r_expr: self.add_expression(*r_expr, interner),
+            },
+            ExpressionKind::Postfix { expr, op } => ExprKind::Postfix {
+                expr: self.add_expression(*expr, interner),
+                op,
+            },
+            ExpressionKind::Index { expr, index } => ExprKind::Index {
+                expr: self.add_expression(*expr, interner),
+                index: self.add_expression(*index, interner),
+            },
+            ExpressionKind::Grouped(expr) => {
+                ExprKind::Grouped(self.add_expression(*expr, interner))
+            }
+            ExpressionKind::Sequence(exprs) => {
+                let ids = exprs
+                    .into_iter()
+                    .map(|expr| self.add_expression(expr, interner))
+                    .collect();
+                ExprKind::Sequence(push_list(&mut self.expr_lists, ids))
+            }
+            ExpressionKind::FunctionRef(ident) => {
+                ExprKind::FunctionRef(ident.map_str(&mut |s| interner.intern(s)))
+            }
+            ExpressionKind::FunctionCall { ident, args } => {
+                let args = args
+                    .into_iter()
+                    .map(|arg| match arg {
+                        ast::Arg::Anon(value) => Arg::Anon(self.add_expression(value, interner)),
+                        ast::Arg::Named(name, value) => {
+                            let name = name.map_str(&mut |s| interner.intern(s));
+                            Arg::Named(name, self.add_expression(value, interner))
+                        }
+                    })
+                    .collect();
+                ExprKind::FunctionCall {
+                    ident: ident.map_str(&mut |s| interner.intern(s)),
+                    args: push_list(&mut self.args, args),
+                }
+            }
+            ExpressionKind::Assign { target, op, expr } => ExprKind::Assign {
+                target: self.add_expression(*target, interner),
+                op,
+                expr: self.add_expression(*expr, interner),
+            },
+        };
+        self.exprs.push(Node {
+            kind,
+            span: expr.span,
+        });
+        ExprId(self.exprs.len() as u32 - 1)
+    }
+
+    pub fn expr(&self, id: ExprId) -> &Node<ExprKind> {
+        &self.exprs[id.0 as usize]
+    }
+
+    pub fn stmt(&self, id: StmtId) -> &Node<StmtKind> {
+        &self.stmts[id.0 as usize]
+    }
+
+    pub fn exprs(&self, list: List<ExprId>) -> &[ExprId] {
+        &self.expr_lists[list.range()]
+    }
+
+    pub fn stmts(&self, list: List<StmtId>) -> &[StmtId] {
+        &self.stmt_lists[list.range()]
+    }
+
+    pub fn idents(&self, list: List<Ident<Symbol>>) -> &[Ident<Symbol>] {
+        &self.idents[list.range()]
+    }
+
+    pub fn args(&self, list: List<Arg>) -> &[Arg] {
+        &self.args[list.range()]
+    }
+
+    pub fn vars(&self, list: List<VarDecl>) -> &[VarDecl] {
+        &self.vars[list.range()]
+    }
+
+    /// Number of expressions and statements in the arena.
+    pub fn node_count(&self) -> usize {
+        self.exprs.len() + self.stmts.len()
+    }
+
+    /// Releases the memory that was reserved for nodes that are not added.
+    pub fn shrink_to_fit(&mut self) {
+        self.exprs.shrink_to_fit();
+        self.stmts.shrink_to_fit();
+        self.expr_lists.shrink_to_fit();
+        self.stmt_lists.shrink_to_fit();
+        self.idents.shrink_to_fit();
+        self.args.shrink_to_fit();
+        self.vars.shrink_to_fit();
+    }
+}
+
+/// Appends `items` to `pool`. The items of a list have to be added at once,
+/// after their children, so that they are stored next to each other.
+fn push_list<T>(pool: &mut Vec<T>, items: Vec<T>) -> List<T> {
+    let start = pool.len() as u32;
+    let len = items.len() as u32;
+    pool.extend(items);
+    List {
+        start,
+        len,
+        marker: PhantomData,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::parser::Parser;
+
+    #[test]
+    fn add_file() {
+        let code = "function f(a) { return a + 1; }\nx = f(a:2, 3);\nx = x;";
+        let stmts = Parser::new(code).parse().unwrap();
+        let mut interner = Interner::new();
+        let mut arena = Arena::new();
+        let file = arena.add_file(stmts, &mut interner);
+        assert_eq!(arena.stmts(file).len(), 3);
+        assert_eq!(arena.node_count(), 16);
+
+        let def = arena.stmt(arena.stmts(file)[0]);
+        assert_eq!(&code[def.span().start..def.span().end], &code[..31]);
+        let body = match def.kind {
+            StmtKind::FunctionDef {
+                ident,
+                params,
+                body,
+                ..
+            } => {
+                assert_eq!(interner.resolve(ident.name), "f");
+                let params = arena.idents(params);
+                assert_eq!(params.len(), 1);
+                assert_eq!(params[0].name, interner.get("a").unwrap());
+                body
+            }
+            _ => panic!("unexpected statement {:?}", def),
+        };
+        match arena.stmt(body).kind {
+            StmtKind::Block { statements } => assert_eq!(arena.stmts(statements).len(), 1),
+            _ => panic!("unexpected statement {:?}", arena.stmt(body)),
+        }
+
+        let call = match arena.stmt(arena.stmts(file)[1]).kind {
+            StmtKind::Expression(expr) => match arena.expr(expr).kind {
+                ExprKind::Assign { expr, .. } => arena.expr(expr),
+                _ => panic!("unexpected expression {:?}", arena.expr(expr)),
+            },
+            _ => panic!("unexpected statement"),
+        };
+        match call.kind {
+            ExprKind::FunctionCall { ident, args } => {
+                assert_eq!(interner.resolve(ident.name), "f");
+                match arena.args(args) {
+                    [Arg::Named(name, named), Arg::Anon(anon)] => {
+                        assert_eq!(name.name, interner.get("a").unwrap());
+                        assert_eq!(arena.expr(*named).kind, ExprKind::Number(2));
+                        assert_eq!(arena.expr(*anon).kind, ExprKind::Number(3));
+                    }
+                    args => panic!("unexpected arguments {:?}", args),
+                }
+            }
+            _ => panic!("unexpected expression {:?}", call),
+        }
+
+        // `f`, `a`, `x`
+        assert_eq!(interner.len(), 3);
+    }
+}
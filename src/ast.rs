@@ -1,79 +1,878 @@
+//! Abstract syntax tree of the Nasl language.
+//!
+//! The tree is generic over the type `S` of the strings it contains. The
+//! parser produces `&'a str`s that point into the source, which is cheap but
+//! ties the tree to the lifetime of the source. `Statement::into_owned`
+//! converts such a tree into an `OwnedStatement`, which can outlive the
+//! source and be sent to other threads.
+//!
+//! # JSON
+//!
+//! With the `serde` feature, the tree can be serialized, e.g. to JSON for
+//! tools written in other languages, and deserialized back into an
+//! `OwnedStatement`. The format is:
+//!
+//! - A file is an array of statements.
+//! - A statement or expression is an object with the kind of the node in
+//!   `"type"`, the fields of that kind in `"value"` and its location in
+//!   `"span"`. `"value"` is left out for kinds without fields, like `Null`
+//!   or `Break`. The kinds and fields are named like the variants and
+//!   fields of `StatementKind` and `ExpressionKind`.
+//! - A span is `{"start": 0, "end": 5, "line": 1, "col": 1}`, see `Span`.
+//! - An `Ident` is `{"name": "port", "span": ...}`, a named argument is a
+//!   `[ident, expression]` pair.
+//! - Operators are strings as written in Nasl, e.g. `"+="` or `">!<"`.
+//!   `VarScope`s are `"Local"` or `"Global"`.
+//! - Missing optional children are `null`.
+//!
+//! For example `x = -1;` is:
+//!
+//! ```json
+//! [{"type": "Expression",
+//!   "value": {"type": "Assign",
+//!             "value": {"target": {"type": "Identifier", "value": "x",
+//!                                  "span": {"start": 0, "end": 1, "line": 1, "col": 1}},
+//!                       "op": "=",
+//!                       "expr": {"type": "Prefix",
+//!                                "value": {"op": "-",
+//!                                          "expr": {"type": "Number", "value": 1,
+//!                                                   "span": {"start": 5, "end": 6, "line": 1, "col": 6}}},
+//!                                "span": {"start": 4, "end": 6, "line": 1, "col": 5}}},
+//!             "span": {"start": 0, "end": 6, "line": 1, "col": 1}},
+//!   "span": {"start": 0, "end": 7, "line": 1, "col": 1}}]
+//! ```
+
+use crate::span::Span;
+use crate::token::TokenType;
 use std::collections::HashMap;
+use std::fmt;
+use std::sync::Arc;
+
+/// Operators that take two operands, `$l_expr $op $r_expr`.
+#[derive(Debug, PartialEq, Eq, Hash, Clone, Copy)]
+pub enum BinaryOp {
+    Add,        // +
+    Sub,        // -
+    Mul,        // *
+    Div,        // /
+    Mod,        // %
+    Pow,        // **
+    Eq,         // ==
+    NotEq,      // !=
+    Gt,         // >
+    Lt,         // <
+    GtEq,       // >=
+    LtEq,       // <=
+    And,        // &&
+    Or,         // ||
+    BitAnd,     // &
+    BitOr,      // |
+    BitXor,     // ^
+    Shl,        // <<
+    Shr,        // >>
+    UShr,       // >>>
+    Substr,     // ><
+    NotSubstr,  // >!<
+    ReMatch,    // =~
+    NotReMatch, // !~
+}
+
+impl BinaryOp {
+    pub fn from_token(ty: &TokenType) -> Option<Self> {
+        let op = match ty {
+            TokenType::Plus => BinaryOp::Add,
+            TokenType::Minus => BinaryOp::Sub,
+            TokenType::Mul => BinaryOp::Mul,
+            TokenType::Div => BinaryOp::Div,
+            TokenType::Mod => BinaryOp::Mod,
+            TokenType::Pow => BinaryOp::Pow,
+            TokenType::Equ => BinaryOp::Eq,
+            TokenType::Nequ => BinaryOp::NotEq,
+            TokenType::Gt => BinaryOp::Gt,
+            TokenType::Lt => BinaryOp::Lt,
+            TokenType::Gte => BinaryOp::GtEq,
+            TokenType::Lte => BinaryOp::LtEq,
+            TokenType::And => BinaryOp::And,
+            TokenType::Or => BinaryOp::Or,
+            TokenType::BwAnd => BinaryOp::BitAnd,
+            TokenType::BwOr => BinaryOp::BitOr,
+            TokenType::BwXor => BinaryOp::BitXor,
+            TokenType::Shl => BinaryOp::Shl,
+            TokenType::Shr => BinaryOp::Shr,
+            TokenType::Ushr => BinaryOp::UShr,
+            TokenType::Substr => BinaryOp::Substr,
+            TokenType::NSUBSTR => BinaryOp::NotSubstr,
+            TokenType::ReMatch => BinaryOp::ReMatch,
+            TokenType::NreMatch => BinaryOp::NotReMatch,
+            _ => return None,
+        };
+        Some(op)
+    }
+
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            BinaryOp::Add => "+",
+            BinaryOp::Sub => "-",
+            BinaryOp::Mul => "*",
+            BinaryOp::Div => "/",
+            BinaryOp::Mod => "%",
+            BinaryOp::Pow => "**",
+            BinaryOp::Eq => "==",
+            BinaryOp::NotEq => "!=",
+            BinaryOp::Gt => ">",
+            BinaryOp::Lt => "<",
+            BinaryOp::GtEq => ">=",
+            BinaryOp::LtEq => "<=",
+            BinaryOp::And => "&&",
+            BinaryOp::Or => "||",
+            BinaryOp::BitAnd => "&",
+            BinaryOp::BitOr => "|",
+            BinaryOp::BitXor => "^",
+            BinaryOp::Shl => "<<",
+            BinaryOp::Shr => ">>",
+            BinaryOp::UShr => ">>>",
+            BinaryOp::Substr => "><",
+            BinaryOp::NotSubstr => ">!<",
+            BinaryOp::ReMatch => "=~",
+            BinaryOp::NotReMatch => "!~",
+        }
+    }
+}
+
+impl fmt::Display for BinaryOp {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(self.as_str())
+    }
+}
+
+/// Operators that take a single operand. `Incr` and `Decr` can be used both
+/// as `Prefix` and `Postfix` operators, the rest are prefix only.
+#[derive(Debug, PartialEq, Eq, Hash, Clone, Copy)]
+pub enum UnaryOp {
+    Plus,   // +
+    Minus,  // -
+    Not,    // !
+    BitNot, // ~
+    Incr,   // ++
+    Decr,   // --
+}
+
+impl UnaryOp {
+    pub fn from_token(ty: &TokenType) -> Option<Self> {
+        let op = match ty {
+            TokenType::Plus => UnaryOp::Plus,
+            TokenType::Minus => UnaryOp::Minus,
+            TokenType::Not => UnaryOp::Not,
+            TokenType::BwNot => UnaryOp::BitNot,
+            TokenType::Incr => UnaryOp::Incr,
+            TokenType::Decr => UnaryOp::Decr,
+            _ => return None,
+        };
+        Some(op)
+    }
+
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            UnaryOp::Plus => "+",
+            UnaryOp::Minus => "-",
+            UnaryOp::Not => "!",
+            UnaryOp::BitNot => "~",
+            UnaryOp::Incr => "++",
+            UnaryOp::Decr => "--",
+        }
+    }
+}
+
+impl fmt::Display for UnaryOp {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(self.as_str())
+    }
+}
+
+/// Plain and compound assignment operators, `$target $op $expr`.
+#[derive(Debug, PartialEq, Eq, Hash, Clone, Copy)]
+pub enum AssignOp {
+    Assign, // =
+    Add,    // +=
+    Sub,    // -=
+    Mul,    // *=
+    Div,    // /=
+    Mod,    // %=
+    BitXor, // ^=
+    Shl,    // <<=
+    Shr,    // >>=
+    UShr,   // >>>=
+}
+
+impl AssignOp {
+    pub fn from_token(ty: &TokenType) -> Option<Self> {
+        let op = match ty {
+            TokenType::Assign => AssignOp::Assign,
+            TokenType::PlusEq => AssignOp::Add,
+            TokenType::MinusEq => AssignOp::Sub,
+            TokenType::MulEq => AssignOp::Mul,
+            TokenType::DivEq => AssignOp::Div,
+            TokenType::ModEq => AssignOp::Mod,
+            TokenType::XorEq => AssignOp::BitXor,
+            TokenType::ShlEq => AssignOp::Shl,
+            TokenType::ShrEq => AssignOp::Shr,
+            TokenType::UshrEq => AssignOp::UShr,
+            _ => return None,
+        };
+        Some(op)
+    }
+
+    /// The binary operator a compound assignment applies, `None` for `=`.
+    pub fn binary_op(&self) -> Option<BinaryOp> {
+        let op = match self {
+            AssignOp::Assign => return None,
+            AssignOp::Add => BinaryOp::Add,
+            AssignOp::Sub => BinaryOp::Sub,
+            AssignOp::Mul => BinaryOp::Mul,
+            AssignOp::Div => BinaryOp::Div,
+            AssignOp::Mod => BinaryOp::Mod,
+            AssignOp::BitXor => BinaryOp::BitXor,
+            AssignOp::Shl => BinaryOp::Shl,
+            AssignOp::Shr => BinaryOp::Shr,
+            AssignOp::UShr => BinaryOp::UShr,
+        };
+        Some(op)
+    }
+
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            AssignOp::Assign => "=",
+            AssignOp::Add => "+=",
+            AssignOp::Sub => "-=",
+            AssignOp::Mul => "*=",
+            AssignOp::Div => "/=",
+            AssignOp::Mod => "%=",
+            AssignOp::BitXor => "^=",
+            AssignOp::Shl => "<<=",
+            AssignOp::Shr => ">>=",
+            AssignOp::UShr => ">>>=",
+        }
+    }
+}
+
+impl fmt::Display for AssignOp {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(self.as_str())
+    }
+}
+
+/// Operators are serialized as they are written in Nasl, e.g. `"+="`.
+#[cfg(feature = "serde")]
+macro_rules! serde_as_str {
+    ($($op:ident),*) => {$(
+        impl serde::Serialize for $op {
+            fn serialize<T: serde::Serializer>(&self, serializer: T) -> Result<T::Ok, T::Error> {
+                serializer.serialize_str(self.as_str())
+            }
+        }
+
+        impl<'de> serde::Deserialize<'de> for $op {
+            fn deserialize<D: serde::Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+                use serde::de::Error;
+
+                let s = String::deserialize(deserializer)?;
+                crate::lexer::Lexer::new(&s)
+                    .next_token()
+                    .ok()
+                    .filter(|token| token.span.len() == s.len())
+                    .and_then(|token| $op::from_token(&token.ty))
+                    .ok_or_else(|| D::Error::custom(format!("unknown operator `{}`", s)))
+            }
+        }
+    )*};
+}
+
+#[cfg(feature = "serde")]
+serde_as_str!(BinaryOp, UnaryOp, AssignOp);
+
+/// A name as written in the source, e.g. a function parameter.
+#[derive(Debug, PartialEq, Eq, Hash, Clone, Copy)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct Ident<S> {
+    pub name: S,
+    pub span: Span,
+}
+
+impl<S> Ident<S> {
+    pub fn span(&self) -> Span {
+        self.span
+    }
+
+    pub fn map_str<T, F: FnMut(S) -> T>(self, f: &mut F) -> Ident<T> {
+        Ident {
+            name: f(self.name),
+            span: self.span,
+        }
+    }
+}
+
+/// Scope of the variables declared by `local_var` and `global_var`.
+#[derive(Debug, PartialEq, Eq, Hash, Clone, Copy)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub enum VarScope {
+    Local,  // local_var
+    Global, // global_var
+}
+
+/// A single variable of a declaration, `$ident` or `$ident = $init`.
+#[derive(Debug, PartialEq, Eq, Hash, Clone)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct VarDecl<S> {
+    pub ident: Ident<S>,
+    pub init: Option<Box<Expression<S>>>,
+}
+
+impl<S> VarDecl<S> {
+    /// Span from the name up to the end of the initializer, if any.
+    pub fn span(&self) -> Span {
+        match &self.init {
+            Some(init) => self.ident.span.to(init.span),
+            None => self.ident.span,
+        }
+    }
+
+    pub fn map_str<T, F: FnMut(S) -> T>(self, f: &mut F) -> VarDecl<T> {
+        VarDecl {
+            ident: self.ident.map_str(f),
+            init: self.init.map(|init| Box::new(init.map_str(f))),
+        }
+    }
+}
+
+/// An argument of a function call.
+#[derive(Debug, PartialEq, Eq, Hash, Clone)]
+#[cfg_attr(
+    feature = "serde",
+    derive(serde::Serialize, serde::Deserialize),
+    serde(tag = "type", content = "value")
+)]
+pub enum Arg<S> {
+    // $value
+    Anon(Expression<S>),
+    // $name: $value
+    Named(Ident<S>, Expression<S>),
+}
+
+impl<S> Arg<S> {
+    /// Name of a named argument.
+    pub fn name(&self) -> Option<&Ident<S>> {
+        match self {
+            Arg::Anon(_) => None,
+            Arg::Named(name, _) => Some(name),
+        }
+    }
+
+    pub fn value(&self) -> &Expression<S> {
+        match self {
+            Arg::Anon(value) | Arg::Named(_, value) => value,
+        }
+    }
 
-#[derive(Debug)]
-pub enum Expression<'a> {
-    Identifier(&'a str),
-    Number(i32),
+    /// Span of the value, including the name of a named argument.
+    pub fn span(&self) -> Span {
+        match self {
+            Arg::Anon(value) => value.span,
+            Arg::Named(name, value) => name.span.to(value.span),
+        }
+    }
+
+    pub fn map_str<T, F: FnMut(S) -> T>(self, f: &mut F) -> Arg<T> {
+        match self {
+            Arg::Anon(value) => Arg::Anon(value.map_str(f)),
+            Arg::Named(name, value) => Arg::Named(name.map_str(f), value.map_str(f)),
+        }
+    }
+}
+
+/// An expression together with its location in the source.
+#[derive(Debug, PartialEq, Eq, Hash, Clone)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct Expression<S> {
+    #[cfg_attr(feature = "serde", serde(flatten))]
+    pub kind: ExpressionKind<S>,
+    pub span: Span,
+}
+
+impl<S> Expression<S> {
+    pub fn new(kind: ExpressionKind<S>, span: Span) -> Self {
+        Expression { kind, span }
+    }
+
+    pub fn span(&self) -> Span {
+        self.span
+    }
+
+    /// Converts every string in the expression with `f`.
+    pub fn map_str<T, F: FnMut(S) -> T>(self, f: &mut F) -> Expression<T> {
+        let mut map = |expr: Box<Expression<S>>| Box::new(expr.map_str(f));
+        let kind = match self.kind {
+            ExpressionKind::Identifier(name) => ExpressionKind::Identifier(f(name)),
+            ExpressionKind::Number(value) => ExpressionKind::Number(value),
+            ExpressionKind::String(value) => ExpressionKind::String(f(value)),
+            ExpressionKind::Data(value) => ExpressionKind::Data(f(value)),
+            ExpressionKind::Boolean(value) => ExpressionKind::Boolean(value),
+            ExpressionKind::Null => ExpressionKind::Null,
+            ExpressionKind::Prefix { op, expr } => ExpressionKind::Prefix {
+                op,
+                expr: map(expr),
+            },
+            ExpressionKind::Infix { l_expr, op, r_expr } => ExpressionKind::Infix {
+                l_expr: map(l_expr),
+                op,
+                r_expr: map(r_expr),
+            },
+            ExpressionKind::Postfix { expr, op } => ExpressionKind::Postfix {
+                expr: map(expr),
+                op,
+            },
+            ExpressionKind::Index { expr, index } => ExpressionKind::Index {
+                expr: map(expr),
+                index: map(index),
+            },
+            ExpressionKind::Grouped(expr) => ExpressionKind::Grouped(map(expr)),
+            ExpressionKind::Sequence(exprs) => {
+                ExpressionKind::Sequence(exprs.into_iter().map(|e| e.map_str(f)).collect())
+            }
+            ExpressionKind::FunctionRef(ident) => ExpressionKind::FunctionRef(ident.map_str(f)),
+            ExpressionKind::FunctionCall { ident, args } => ExpressionKind::FunctionCall {
+                ident: ident.map_str(f),
+                args: args.into_iter().map(|arg| arg.map_str(f)).collect(),
+            },
+            ExpressionKind::Assign { target, op, expr } => ExpressionKind::Assign {
+                target: map(target),
+                op,
+                expr: map(expr),
+            },
+        };
+        Expression::new(kind, self.span)
+    }
+}
+
+impl Expression<&str> {
+    /// Copies the strings of the expression out of the source.
+    pub fn into_owned(self) -> OwnedExpression {
+        self.map_str(&mut owned_strings())
+    }
+}
+
+#[derive(Debug, PartialEq, Eq, Hash, Clone)]
+#[cfg_attr(
+    feature = "serde",
+    derive(serde::Serialize, serde::Deserialize),
+    serde(tag = "type", content = "value")
+)]
+pub enum ExpressionKind<S> {
+    Identifier(S),
+    Number(i64),
+    // "$str", NASL `string`. The content is taken verbatim.
+    String(S),
+    // '$data', NASL `data`. The content is kept as written, escape sequences
+    // are interpreted at runtime (see `lexer::unescape`).
+    Data(S),
+    // TRUE, FALSE
+    Boolean(bool),
+    // NULL
+    Null,
+    // $op$expr
     Prefix {
-        op: &'a str,
-        expr: Box<Expression<'a>>
+        op: UnaryOp,
+        expr: Box<Expression<S>>,
     },
+    // $l_expr $op $r_expr
     Infix {
-        l_expr: Box<Expression<'a>>,
-        op: &'a str,
-        r_expr: Box<Expression<'a>>
+        l_expr: Box<Expression<S>>,
+        op: BinaryOp,
+        r_expr: Box<Expression<S>>,
     },
+    // $expr$op
     Postfix {
-        expr: &'a str,
-        op: Box<Expression<'a>>
+        expr: Box<Expression<S>>,
+        op: UnaryOp,
     },
-    // $ident($anon_args, $named_args);
+    // $expr[$index]
+    Index {
+        expr: Box<Expression<S>>,
+        index: Box<Expression<S>>,
+    },
+    // ($expr)
+    //
+    // Kept in the tree so that the parentheses written in the source are not
+    // lost.
+    Grouped(Box<Expression<S>>),
+    // $expr, $expr, ...
+    //
+    // Only allowed in the initializer and the step of a `for` loop.
+    Sequence(Vec<Expression<S>>),
+    // @$ident, a reference to a user defined function.
+    FunctionRef(Ident<S>),
+    // $ident($args);
+    //
+    // Anonymous and named arguments are kept in the order in which they are
+    // written.
     FunctionCall {
-        ident: &'a str,
-        named_args: HashMap<&'a str, Expression<'a>>,
-        anon_args: Vec<Expression<'a>>
+        ident: Ident<S>,
+        args: Vec<Arg<S>>,
     },
-    // $ident = $expr;
+    // $target $op $expr;
     Assign {
-        ident: &'a str,
-        expr: Box<Expression<'a>>
+        target: Box<Expression<S>>,
+        op: AssignOp,
+        expr: Box<Expression<S>>,
+    },
+}
+
+/// A statement together with its location in the source.
+///
+/// The span covers the whole statement, including the terminating `;`.
+#[derive(Debug, PartialEq, Eq, Hash, Clone)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct Statement<S> {
+    #[cfg_attr(feature = "serde", serde(flatten))]
+    pub kind: StatementKind<S>,
+    pub span: Span,
+}
+
+impl<S> Statement<S> {
+    pub fn new(kind: StatementKind<S>, span: Span) -> Self {
+        Statement { kind, span }
+    }
+
+    pub fn span(&self) -> Span {
+        self.span
+    }
+
+    /// Whether an `else` written after the statement would be taken by an
+    /// `if` inside of it.
+    pub(crate) fn ends_with_open_if(&self) -> bool {
+        match &self.kind {
+            StatementKind::If {
+                else_block: None, ..
+            } => true,
+            StatementKind::If {
+                else_block: Some(block),
+                ..
+            }
+            | StatementKind::While { block, .. }
+            | StatementKind::For { block, .. }
+            | StatementKind::Foreach { block, .. } => block.ends_with_open_if(),
+            _ => false,
+        }
+    }
+
+    /// Converts every string in the statement with `f`.
+    pub fn map_str<T, F: FnMut(S) -> T>(self, f: &mut F) -> Statement<T> {
+        let kind = match self.kind {
+            StatementKind::If {
+                cond,
+                if_block,
+                else_block,
+            } => StatementKind::If {
+                cond: Box::new(cond.map_str(f)),
+                if_block: Box::new(if_block.map_str(f)),
+                else_block: else_block.map(|block| Box::new(block.map_str(f))),
+            },
+            StatementKind::While { cond, block } => StatementKind::While {
+                cond: Box::new(cond.map_str(f)),
+                block: Box::new(block.map_str(f)),
+            },
+            StatementKind::For {
+                init_expr,
+                cond,
+                end_expr,
+                block,
+            } => StatementKind::For {
+                init_expr: init_expr.map(|expr| Box::new(expr.map_str(f))),
+                cond: cond.map(|expr| Box::new(expr.map_str(f))),
+                end_expr: end_expr.map(|expr| Box::new(expr.map_str(f))),
+                block: Box::new(block.map_str(f)),
+            },
+            StatementKind::Foreach { var, array, block } => StatementKind::Foreach {
+                var: var.map_str(f),
+                array: Box::new(array.map_str(f)),
+                block: Box::new(block.map_str(f)),
+            },
+            StatementKind::Repeat { block, cond } => StatementKind::Repeat {
+                block: Box::new(block.map_str(f)),
+                cond: Box::new(cond.map_str(f)),
+            },
+            StatementKind::FunctionDef {
+                ident,
+                params,
+                uses_anon_args,
+                body,
+            } => StatementKind::FunctionDef {
+                ident: ident.map_str(f),
+                params: params.into_iter().map(|p| p.map_str(f)).collect(),
+                uses_anon_args,
+                body: Box::new(body.map_str(f)),
+            },
+            StatementKind::Declaration { scope, vars } => StatementKind::Declaration {
+                scope,
+                vars: vars.into_iter().map(|var| var.map_str(f)).collect(),
+            },
+            StatementKind::Include { path, path_span } => StatementKind::Include {
+                path: f(path),
+                path_span,
+            },
+            StatementKind::Return { expr } => StatementKind::Return {
+                expr: expr.map(|expr| Box::new(expr.map_str(f))),
+            },
+            StatementKind::Break => StatementKind::Break,
+            StatementKind::Continue => StatementKind::Continue,
+            StatementKind::Empty => StatementKind::Empty,
+            StatementKind::Block { statements } => StatementKind::Block {
+                statements: statements.into_iter().map(|s| s.map_str(f)).collect(),
+            },
+            StatementKind::Expression(expr) => StatementKind::Expression(Box::new(expr.map_str(f))),
+            StatementKind::Repeated { call, count } => StatementKind::Repeated {
+                call: Box::new(call.map_str(f)),
+                count: Box::new(count.map_str(f)),
+            },
+        };
+        Statement::new(kind, self.span)
     }
 }
 
-#[derive(Debug)]
-pub enum Statement<'a> {
+impl Statement<&str> {
+    /// Copies the strings of the statement out of the source.
+    pub fn into_owned(self) -> OwnedStatement {
+        self.map_str(&mut owned_strings())
+    }
+}
+
+#[derive(Debug, PartialEq, Eq, Hash, Clone)]
+#[cfg_attr(
+    feature = "serde",
+    derive(serde::Serialize, serde::Deserialize),
+    serde(tag = "type", content = "value")
+)]
+pub enum StatementKind<S> {
     // if ($cond) $if_block
     // else $else_block
     If {
-        cond: Box<Expression<'a>>,
-        if_block: Box<Statement<'a>>,
-        else_block: Option<Box<Statement<'a>>>
+        cond: Box<Expression<S>>,
+        if_block: Box<Statement<S>>,
+        else_block: Option<Box<Statement<S>>>,
     },
     // while ($cond) $block;
     While {
-        cond: Box<Expression<'a>>,
-        block: Box<Statement<'a>>
+        cond: Box<Expression<S>>,
+        block: Box<Statement<S>>,
     },
     // for ($init_expr; $cond; $end_expr) $block;
+    //
+    // `$init_expr` and `$end_expr` can be `ExpressionKind::Sequence`s.
     For {
-        init_expr: Option<Box<Expression<'a>>>,
-        cond: Option<Box<Expression<'a>>>,
-        end_expr: Option<Box<Expression<'a>>>,
-        block: Box<Statement<'a>>
+        init_expr: Option<Box<Expression<S>>>,
+        cond: Option<Box<Expression<S>>>,
+        end_expr: Option<Box<Expression<S>>>,
+        block: Box<Statement<S>>,
     },
     // foreach $var ($array) $block;
+    //
+    // `$var` is bound to each value (not key) of `$array` in turn. `$array`
+    // may be any expression, e.g. `foreach k (keys(arr))`.
     Foreach {
-        var: Box<Statement<'a>>,
-        array: Box<Statement<'a>>,
-        block: Box<Statement<'a>>
+        var: Ident<S>,
+        array: Box<Expression<S>>,
+        block: Box<Statement<S>>,
     },
     // repeat $block; until ($cond);
     Repeat {
-        block: Box<Statement<'a>>,
-        cond: Box<Expression<'a>>,
+        block: Box<Statement<S>>,
+        cond: Box<Expression<S>>,
     },
-    // function $ident ($args) { body }
+    // function $ident ($params) { body }
+    //
+    // Parameters can only be passed by name. If `uses_anon_args` is set, the
+    // body reads `_FCT_ANON_ARGS`, so the function also accepts any number
+    // of anonymous (positional) arguments.
     FunctionDef {
-        ident: &'a str,
-        args: Vec<Expression<'a>>,
-        body: Box<Statement<'a>>,
+        ident: Ident<S>,
+        params: Vec<Ident<S>>,
+        uses_anon_args: bool,
+        body: Box<Statement<S>>,
+    },
+    // local_var $vars; or global_var $vars;
+    Declaration {
+        scope: VarScope,
+        vars: Vec<VarDecl<S>>,
+    },
+    // include($path);
+    //
+    // `path_span` is the location of the `$path` literal.
+    Include {
+        path: S,
+        path_span: Span,
     },
     // return $expr;
     Return {
-        expr: Option<Box<Expression<'a>>>,
+        expr: Option<Box<Expression<S>>>,
     },
+    // break;
+    Break,
+    // continue;
+    Continue,
+    // ;
+    Empty,
     Block {
-        statements: Vec<Statement<'a>>,
+        statements: Vec<Statement<S>>,
     },
-    Expression(Box<Expression<'a>>),
-}
\ No newline at end of file
+    Expression(Box<Expression<S>>),
+    // $call x $count;
+    //
+    // Evaluates the function call `$count` times.
+    Repeated {
+        call: Box<Expression<S>>,
+        count: Box<Expression<S>>,
+    },
+}
+
+/// Statement that does not borrow from the source.
+pub type OwnedStatement = Statement<Arc<str>>;
+/// Expression that does not borrow from the source.
+pub type OwnedExpression = Expression<Arc<str>>;
+
+/// Copies a whole file out of the source. Equal strings, e.g. the names of
+/// often used variables, share a single allocation.
+pub fn into_owned(stmts: Vec<Statement<&str>>) -> Vec<OwnedStatement> {
+    let mut f = owned_strings();
+    stmts.into_iter().map(|stmt| stmt.map_str(&mut f)).collect()
+}
+
+fn owned_strings<'a>() -> impl FnMut(&'a str) -> Arc<str> {
+    let mut strings: HashMap<&'a str, Arc<str>> = HashMap::new();
+    move |s| strings.entry(s).or_insert_with(|| Arc::from(s)).clone()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::parser::Parser;
+    use crate::token::Token;
+
+    #[test]
+    fn operators_round_trip() {
+        let binary = [
+            "+", "-", "*", "/", "%", "**", "==", "!=", ">", "<", ">=", "<=", "&&", "||", "&", "|",
+            "^", "<<", ">>", ">>>", "><", ">!<", "=~", "!~",
+        ];
+        for op in &binary {
+            let ty = Token::from_op(op).ty;
+            assert_eq!(op, &BinaryOp::from_token(&ty).unwrap().to_string());
+        }
+
+        for op in &["+", "-", "!", "~", "++", "--"] {
+            let ty = Token::from_op(op).ty;
+            assert_eq!(op, &UnaryOp::from_token(&ty).unwrap().to_string());
+        }
+
+        for op in &[
+            "=", "+=", "-=", "*=", "/=", "%=", "^=", "<<=", ">>=", ">>>=",
+        ] {
+            let ty = Token::from_op(op).ty;
+            assert_eq!(op, &AssignOp::from_token(&ty).unwrap().to_string());
+        }
+    }
+
+    #[test]
+    fn non_operators() {
+        assert_eq!(None, BinaryOp::from_token(&TokenType::Incr));
+        assert_eq!(None, UnaryOp::from_token(&TokenType::Mul));
+        assert_eq!(None, AssignOp::from_token(&TokenType::Equ));
+    }
+
+    #[test]
+    fn owned_tree() {
+        let source = String::from("function f(a) { return a + b; }\nf(a:b, 'c');");
+        let stmts = Parser::new(&source).parse().unwrap();
+        let expected = format!("{:?}", stmts);
+        let owned = into_owned(stmts);
+        drop(source);
+
+        let debug = std::thread::spawn(move || format!("{:?}", owned))
+            .join()
+            .unwrap();
+        assert_eq!(debug, expected);
+    }
+
+    #[test]
+    fn owned_strings_are_shared() {
+        let stmts = Parser::new("a = a;").parse().unwrap();
+        let stmt = stmts.into_iter().next().unwrap().into_owned();
+        match stmt.kind {
+            StatementKind::Expression(expr) => match expr.kind {
+                ExpressionKind::Assign { target, expr, .. } => match (target.kind, expr.kind) {
+                    (ExpressionKind::Identifier(l), ExpressionKind::Identifier(r)) => {
+                        assert!(Arc::ptr_eq(&l, &r))
+                    }
+                    kinds => panic!("unexpected expressions {:?}", kinds),
+                },
+                kind => panic!("unexpected expression {:?}", kind),
+            },
+            kind => panic!("unexpected statement {:?}", kind),
+        }
+    }
+
+    #[cfg(feature = "serde")]
+    #[test]
+    fn json_schema() {
+        let stmts = Parser::new("x = -1;").parse().unwrap();
+        let span = |start, end, col| serde_json::json!({"start": start, "end": end, "line": 1, "col": col});
+        let expected = serde_json::json!([{
+            "type": "Expression",
+            "value": {
+                "type": "Assign",
+                "value": {
+                    "target": {"type": "Identifier", "value": "x", "span": span(0, 1, 1)},
+                    "op": "=",
+                    "expr": {
+                        "type": "Prefix",
+                        "value": {
+                            "op": "-",
+                            "expr": {"type": "Number", "value": 1, "span": span(5, 6, 6)},
+                        },
+                        "span": span(4, 6, 5),
+                    },
+                },
+                "span": span(0, 6, 1),
+            },
+            "span": span(0, 7, 1),
+        }]);
+        assert_eq!(serde_json::to_value(&stmts).unwrap(), expected);
+    }
+
+    #[cfg(feature = "serde")]
+    #[test]
+    fn json_round_trip() {
+        let code = r#"
+            include("http_func.inc");
+            function f(a, b) {
+                local_var c = a ** 2, d;
+                global_var g;
+                for (i = 0, j = 1; i < 3; i++) c += f(a:i, b:j)[0];
+                foreach x (l) { if (x >!< "a") break; else continue; }
+                repeat d--; until (d == NULL);
+                while (TRUE) return @f;
+                return _FCT_ANON_ARGS;
+            }
+            send(data:'\r\n', ~1, !FALSE) x 3;
+        "#;
+        let owned = into_owned(Parser::new(code).parse().unwrap());
+        let json = serde_json::to_string(&owned).unwrap();
+        let back: Vec<OwnedStatement> = serde_json::from_str(&json).unwrap();
+        assert_eq!(back, owned);
+
+        let error = serde_json::from_str::<BinaryOp>("\"+=\"").unwrap_err();
+        assert!(error.to_string().contains("unknown operator `+=`"));
+        assert_eq!(
+            serde_json::from_str::<AssignOp>("\"+=\"").unwrap(),
+            AssignOp::Add
+        );
+    }
+}
@@ -0,0 +1,294 @@
+//! Names that the Nasl interpreter defines for every script.
+//!
+//! The lists are not complete, the interpreter gained and lost functions
+//! over the years. Functions that a script gets from an include file, like
+//! `http_get_port` from `http_func.inc`, are not builtins.
+
+/// Functions of the interpreter.
+pub const FUNCTIONS: &[&str] = &[
+    // Description of the script.
+    "script_add_preference",
+    "script_bugtraq_id",
+    "script_category",
+    "script_copyright",
+    "script_cve_id",
+    "script_dependencies",
+    "script_exclude_keys",
+    "script_family",
+    "script_get_preference",
+    "script_get_preference_file_content",
+    "script_get_preference_file_location",
+    "script_mandatory_keys",
+    "script_name",
+    "script_oid",
+    "script_require_keys",
+    "script_require_ports",
+    "script_require_udp_ports",
+    "script_tag",
+    "script_timeout",
+    "script_version",
+    "script_xref",
+    "get_script_oid",
+    "get_preference",
+    "safe_checks",
+    "vendor_version",
+    // Knowledge base and reporting.
+    "get_host_kb_index",
+    "get_kb_item",
+    "get_kb_list",
+    "replace_kb_item",
+    "set_kb_item",
+    "error_message",
+    "log_message",
+    "security_message",
+    // Network.
+    "close",
+    "ftp_get_pasv_port",
+    "ftp_log_in",
+    "get_sock_info",
+    "get_source_port",
+    "join_multicast_group",
+    "leave_multicast_group",
+    "open_priv_sock_tcp",
+    "open_priv_sock_udp",
+    "open_sock_kdc",
+    "open_sock_tcp",
+    "open_sock_udp",
+    "recv",
+    "recv_line",
+    "send",
+    "socket_cert_verify",
+    "socket_get_cert",
+    "socket_get_error",
+    "socket_get_ssl_ciphersuite",
+    "socket_get_ssl_session_id",
+    "socket_get_ssl_version",
+    "socket_negotiate_ssl",
+    "telnet_init",
+    "cgibin",
+    "http_close_socket",
+    "http_delete",
+    "http_get",
+    "http_head",
+    "http_open_socket",
+    "http_post",
+    "http_put",
+    // Target and ports.
+    "get_host_ip",
+    "get_host_name",
+    "get_host_names",
+    "get_host_open_port",
+    "get_port_state",
+    "get_port_transport",
+    "get_tcp_port_state",
+    "get_udp_port_state",
+    "islocalhost",
+    "islocalnet",
+    "resolve_host_name",
+    "resolve_hostname_to_multiple_ips",
+    "same_host",
+    "scanner_add_port",
+    "scanner_get_port",
+    "scanner_status",
+    "TARGET_IS_IPV6",
+    "this_host",
+    "this_host_name",
+    // Packets.
+    "dump_icmp_packet",
+    "dump_ip_packet",
+    "dump_tcp_packet",
+    "dump_udp_packet",
+    "forge_icmp_packet",
+    "forge_igmp_packet",
+    "forge_ip_packet",
+    "forge_ipv6_packet",
+    "forge_tcp_packet",
+    "forge_udp_packet",
+    "get_icmp_element",
+    "get_ip_element",
+    "get_tcp_element",
+    "get_tcp_option",
+    "get_udp_element",
+    "insert_ip_options",
+    "insert_tcp_options",
+    "pcap_next",
+    "send_capture",
+    "send_packet",
+    "set_ip_elements",
+    "set_tcp_elements",
+    "set_udp_elements",
+    "tcp_ping",
+    "end_denial",
+    "start_denial",
+    // Strings and data.
+    "chomp",
+    "crap",
+    "display",
+    "egrep",
+    "ereg",
+    "ereg_replace",
+    "eregmatch",
+    "hex",
+    "hexstr",
+    "insstr",
+    "int",
+    "match",
+    "ord",
+    "raw_string",
+    "split",
+    "str_replace",
+    "strcat",
+    "stridx",
+    "string",
+    "strlen",
+    "strstr",
+    "substr",
+    "tolower",
+    "toupper",
+    "base64_decode",
+    "base64_encode",
+    "gunzip",
+    "gzip",
+    // Arrays.
+    "keys",
+    "make_array",
+    "make_list",
+    "max_index",
+    "sort",
+    // Miscellaneous.
+    "defined_func",
+    "dump_ctxt",
+    "exit",
+    "isnull",
+    "rand",
+    "sleep",
+    "typeof",
+    "usleep",
+    "gettimeofday",
+    "localtime",
+    "mktime",
+    "unixtime",
+    "isotime_add",
+    "isotime_is_valid",
+    "isotime_now",
+    "isotime_print",
+    "isotime_scan",
+    // Files.
+    "file_close",
+    "file_open",
+    "file_read",
+    "file_seek",
+    "file_stat",
+    "file_write",
+    "find_in_path",
+    "fread",
+    "fwrite",
+    "get_tmp_dir",
+    "pread",
+    "unlink",
+    // Cryptography.
+    "HMAC_MD2",
+    "HMAC_MD5",
+    "HMAC_RIPEMD160",
+    "HMAC_SHA1",
+    "HMAC_SHA256",
+    "HMAC_SHA384",
+    "HMAC_SHA512",
+    "MD2",
+    "MD4",
+    "MD5",
+    "RIPEMD160",
+    "SHA1",
+    "SHA256",
+    "SHA512",
+    "bn_cmp",
+    "bn_random",
+    "dh_compute_key",
+    "dh_generate_key",
+    "rsa_public_encrypt",
+    "rsa_private_decrypt",
+    "rsa_sign",
+    "pem_to_dsa",
+    "pem_to_rsa",
+    "cert_close",
+    "cert_open",
+    "cert_query",
+    // SSH.
+    "ssh_connect",
+    "ssh_disconnect",
+    "ssh_get_auth_methods",
+    "ssh_get_host_key",
+    "ssh_get_issue_banner",
+    "ssh_get_server_banner",
+    "ssh_get_sock",
+    "ssh_login_interactive",
+    "ssh_login_interactive_pass",
+    "ssh_request_exec",
+    "ssh_session_id_from_sock",
+    "ssh_set_login",
+    "ssh_shell_close",
+    "ssh_shell_open",
+    "ssh_shell_read",
+    "ssh_shell_write",
+    "ssh_userauth",
+    // Other plugins.
+    "plugin_run_find_service",
+    "plugin_run_openvas_tcp_scanner",
+    "plugin_run_synscan",
+];
+
+/// Variables and constants of the interpreter.
+pub const VARIABLES: &[&str] = &[
+    "_FCT_ANON_ARGS",
+    "description",
+    "COMMAND_LINE",
+    "NASL_LEVEL",
+    "OPENVAS_VERSION",
+    "SCRIPT_NAME",
+    // Categories of scripts.
+    "ACT_ATTACK",
+    "ACT_DENIAL",
+    "ACT_DESTRUCTIVE_ATTACK",
+    "ACT_END",
+    "ACT_FLOOD",
+    "ACT_GATHER_INFO",
+    "ACT_INIT",
+    "ACT_KILL_HOST",
+    "ACT_MIXED_ATTACK",
+    "ACT_SCANNER",
+    "ACT_SETTINGS",
+    // Transports of sockets.
+    "ENCAPS_AUTO",
+    "ENCAPS_IP",
+    "ENCAPS_SSLv2",
+    "ENCAPS_SSLv23",
+    "ENCAPS_SSLv3",
+    "ENCAPS_TLSv1",
+    "ENCAPS_TLSv11",
+    "ENCAPS_TLSv12",
+    "ENCAPS_TLSv13",
+    "ENCAPS_TLScustom",
+    "ENCAPS_MAX",
+    // Packets.
+    "IPPROTO_ICMP",
+    "IPPROTO_IGMP",
+    "IPPROTO_IP",
+    "IPPROTO_TCP",
+    "IPPROTO_UDP",
+    "IP_DF",
+    "IP_MF",
+    "IP_OFFMASK",
+    "IP_RF",
+    "TH_ACK",
+    "TH_FIN",
+    "TH_PUSH",
+    "TH_RST",
+    "TH_SYN",
+    "TH_URG",
+    "MSG_OOB",
+    // Errors of sockets.
+    "ECONNRESET",
+    "EUNKNOWN",
+    "ETIMEDOUT",
+    "NOERR",
+];
@@ -0,0 +1,809 @@
+//! Lossless concrete syntax tree of the Nasl language.
+//!
+//! Unlike the AST, the syntax tree keeps every piece of the source: tokens,
+//! parentheses, whitespace and comments. The parser builds it while it
+//! consumes the tokens. Each AST node has a `SyntaxNode` with the same span,
+//! whose children are the nodes of its AST children and the tokens between
+//! them. Whitespace and comments before the first token of a node belong to
+//! the enclosing node.
+//!
+//! A statement that fails to parse becomes an `Error` node, together with
+//! the tokens that the parser skips to recover from the error. Input that
+//! the lexer cannot make sense of is an `Error` token in an `Error` node. So
+//! every file has a syntax tree, even one with syntax errors.
+//!
+//! Concatenating the text of all tokens gives back the source exactly, and
+//! for a file without syntax errors `SyntaxTree::to_ast` gives the same AST
+//! as `Parser::parse`. Tools that
+//! change a file, e.g. rename a function, can therefore find what to change
+//! with the AST or the syntax tree and replace only the text of the affected
+//! tokens, keeping the layout of the rest of the file.
+//!
+//! ```
+//! use nasl_transpiler::parser::Parser;
+//! use nasl_transpiler::token::TokenType;
+//!
+//! let source = "x = f(1); # call f\n";
+//! let (tree, errors) = Parser::new(source).parse_cst();
+//! assert!(errors.is_empty());
+//! let renamed: String = tree
+//!     .tokens()
+//!     .map(|token| match token.text(source) {
+//!         "f" if token.ty == TokenType::Ident => "g",
+//!         text => text,
+//!     })
+//!     .collect();
+//! assert_eq!(renamed, "x = g(1); # call f\n");
+//! ```
+
+use crate::ast::*;
+use crate::error::Diagnostic;
+use crate::span::Span;
+use crate::token::{Token, TokenType};
+use std::fmt::Write;
+
+/// Kind of a `SyntaxNode`, named like the AST node it stands for.
+#[derive(Debug, PartialEq, Eq, Hash, Clone, Copy)]
+pub enum NodeKind {
+    File,
+
+    // Statements
+    If,
+    While,
+    For,
+    Foreach,
+    Repeat,
+    FunctionDef,
+    Declaration,
+    Include,
+    Return,
+    Break,
+    Continue,
+    Empty,
+    Block,
+    ExpressionStatement,
+    Repeated,
+
+    // Expressions
+    Identifier,
+    /// Numbers, strings, data, `TRUE`, `FALSE` and `NULL`.
+    Literal,
+    Prefix,
+    Postfix,
+    Infix,
+    Assign,
+    Index,
+    Grouped,
+    Sequence,
+    FunctionRef,
+    FunctionCall,
+
+    /// `$ident` or `$ident = $init` in a declaration.
+    VarDecl,
+    /// `$ident:$expr` in the arguments of a call.
+    NamedArg,
+
+    /// Input that does not parse, see the documentation of the module.
+    Error,
+}
+
+impl NodeKind {
+    pub(crate) fn of_statement<S>(kind: &StatementKind<S>) -> Self {
+        match kind {
+            StatementKind::If { .. } => NodeKind::If,
+            StatementKind::While { .. } => NodeKind::While,
+            StatementKind::For { .. } => NodeKind::For,
+            StatementKind::Foreach { .. } => NodeKind::Foreach,
+            StatementKind::Repeat { .. } => NodeKind::Repeat,
+            StatementKind::FunctionDef { .. } => NodeKind::FunctionDef,
+            StatementKind::Declaration { .. } => NodeKind::Declaration,
+            StatementKind::Include { .. } => NodeKind::Include,
+            StatementKind::Return { .. } => NodeKind::Return,
+            StatementKind::Break => NodeKind::Break,
+            StatementKind::Continue => NodeKind::Continue,
+            StatementKind::Empty => NodeKind::Empty,
+            StatementKind::Block { .. } => NodeKind::Block,
+            StatementKind::Expression(_) => NodeKind::ExpressionStatement,
+            StatementKind::Repeated { .. } => NodeKind::Repeated,
+        }
+    }
+
+    pub(crate) fn of_expression<S>(kind: &ExpressionKind<S>) -> Self {
+        match kind {
+            ExpressionKind::Identifier(_) => NodeKind::Identifier,
+            ExpressionKind::Number(_)
+            | ExpressionKind::String(_)
+            | ExpressionKind::Data(_)
+            | ExpressionKind::Boolean(_)
+            | ExpressionKind::Null => NodeKind::Literal,
+            ExpressionKind::Prefix { .. } => NodeKind::Prefix,
+            ExpressionKind::Postfix { .. } => NodeKind::Postfix,
+            ExpressionKind::Infix { .. } => NodeKind::Infix,
+            ExpressionKind::Assign { .. } => NodeKind::Assign,
+            ExpressionKind::Index { .. } => NodeKind::Index,
+            ExpressionKind::Grouped(_) => NodeKind::Grouped,
+            ExpressionKind::Sequence(_) => NodeKind::Sequence,
+            ExpressionKind::FunctionRef(_) => NodeKind::FunctionRef,
+            ExpressionKind::FunctionCall { .. } => NodeKind::FunctionCall,
+        }
+    }
+}
+
+/// A token of the source, including whitespace and comments.
+#[derive(Debug, PartialEq, Eq, Clone, Copy)]
+pub struct SyntaxToken {
+    pub ty: TokenType,
+    pub span: Span,
+}
+
+impl SyntaxToken {
+    pub fn text<'s>(&self, source: &'s str) -> &'s str {
+        &source[self.span.start..self.span.end]
+    }
+
+    /// Whether the token is whitespace or a comment.
+    pub fn is_trivia(&self) -> bool {
+        self.ty == TokenType::Whitespace || self.ty == TokenType::Comment
+    }
+}
+
+#[derive(Debug, PartialEq, Eq, Clone)]
+pub enum SyntaxElement {
+    Node(SyntaxNode),
+    Token(SyntaxToken),
+}
+
+impl SyntaxElement {
+    pub fn span(&self) -> Span {
+        match self {
+            SyntaxElement::Node(node) => node.span,
+            SyntaxElement::Token(token) => token.span,
+        }
+    }
+}
+
+#[derive(Debug, PartialEq, Eq, Clone)]
+pub struct SyntaxNode {
+    kind: NodeKind,
+    span: Span,
+    children: Vec<SyntaxElement>,
+}
+
+impl SyntaxNode {
+    pub fn kind(&self) -> NodeKind {
+        self.kind
+    }
+
+    pub fn span(&self) -> Span {
+        self.span
+    }
+
+    /// Nodes and tokens right below this node, in source order.
+    pub fn children(&self) -> &[SyntaxElement] {
+        &self.children
+    }
+
+    /// Nodes right below this node.
+    pub fn child_nodes(&self) -> impl Iterator<Item = &SyntaxNode> {
+        self.children.iter().filter_map(|child| match child {
+            SyntaxElement::Node(node) => Some(node),
+            SyntaxElement::Token(_) => None,
+        })
+    }
+
+    /// Tokens right below this node, without whitespace and comments.
+    pub fn child_tokens(&self) -> impl Iterator<Item = &SyntaxToken> {
+        self.children.iter().filter_map(|child| match child {
+            SyntaxElement::Token(token) if !token.is_trivia() => Some(token),
+            _ => None,
+        })
+    }
+
+    /// All tokens of this node and the nodes below it, in source order.
+    pub fn tokens(&self) -> Tokens<'_> {
+        Tokens {
+            stack: vec![self.children.iter()],
+        }
+    }
+
+    pub fn text<'s>(&self, source: &'s str) -> &'s str {
+        &source[self.span.start..self.span.end]
+    }
+
+    /// First token of type `ty` right below this node.
+    fn token(&self, ty: TokenType) -> Option<&SyntaxToken> {
+        self.child_tokens().find(|token| token.ty == ty)
+    }
+}
+
+/// Iterator over the tokens of a node, see `SyntaxNode::tokens`.
+pub struct Tokens<'t> {
+    stack: Vec<std::slice::Iter<'t, SyntaxElement>>,
+}
+
+impl<'t> Iterator for Tokens<'t> {
+    type Item = &'t SyntaxToken;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        loop {
+            match self.stack.last_mut()?.next() {
+                Some(SyntaxElement::Token(token)) => return Some(token),
+                Some(SyntaxElement::Node(node)) => self.stack.push(node.children.iter()),
+                None => {
+                    self.stack.pop();
+                }
+            }
+        }
+    }
+}
+
+/// Syntax tree of a whole file, built by `Parser::parse_cst`.
+#[derive(Debug, PartialEq, Eq, Clone)]
+pub struct SyntaxTree<'a> {
+    source: &'a str,
+    root: SyntaxNode,
+}
+
+impl<'a> SyntaxTree<'a> {
+    pub fn source(&self) -> &'a str {
+        self.source
+    }
+
+    /// The `File` node.
+    pub fn root(&self) -> &SyntaxNode {
+        &self.root
+    }
+
+    /// All tokens of the file, in source order.
+    pub fn tokens(&self) -> Tokens<'_> {
+        self.root.tokens()
+    }
+
+    /// The source, put together from the tokens.
+    pub fn text(&self) -> String {
+        self.tokens().map(|token| token.text(self.source)).collect()
+    }
+
+    /// The AST of the file, as `Parser::parse` returns it. Fails at the
+    /// first `Error` node.
+    pub fn to_ast(&self) -> Result<Vec<Statement<&'a str>>, Diagnostic> {
+        let mut lowering = Lowering {
+            source: self.source,
+            uses_anon_args: false,
+        };
+        lowering.statements(&self.root)
+    }
+
+    /// Indented dump of the tree, one node or token per line, e.g.
+    /// `Identifier@0..1` followed by `  Ident@0..1 "x"`.
+    pub fn dump(&self) -> String {
+        let mut out = String::new();
+        dump_node(&mut out, &self.root, self.source, 0);
+        out
+    }
+}
+
+fn dump_node(out: &mut String, node: &SyntaxNode, source: &str, indent: usize) {
+    let span = node.span;
+    writeln!(
+        out,
+        "{:indent$}{:?}@{}..{}",
+        "",
+        node.kind,
+        span.start,
+        span.end,
+        indent = indent * 2
+    )
+    .unwrap();
+    for child in &node.children {
+        match child {
+            SyntaxElement::Node(child) => dump_node(out, child, source, indent + 1),
+            SyntaxElement::Token(token) => writeln!(
+                out,
+                "{:indent$}{:?}@{}..{} {:?}",
+                "",
+                token.ty,
+                token.span.start,
+                token.span.end,
+                token.text(source),
+                indent = (indent + 1) * 2
+            )
+            .unwrap(),
+        }
+    }
+}
+
+/// Where a node starts among the children of the nodes that are not
+/// finished yet.
+#[derive(Debug, Default, Clone, Copy)]
+pub(crate) struct Checkpoint(usize);
+
+/// Builds a syntax tree from the tokens that the parser consumes and the
+/// nodes that it finishes.
+///
+/// A node is made of the children that have been added since its
+/// checkpoint, so an infix expression can take in its left hand side after
+/// that has been parsed.
+pub(crate) struct Builder<'a> {
+    source: &'a str,
+    // Tokens and finished nodes whose parent is not finished yet.
+    children: Vec<SyntaxElement>,
+    // Location of the end of the last token, where the next whitespace
+    // starts.
+    pos: usize,
+    line: usize,
+    col: usize,
+    // Number of comments of the lexer that are in the tree already.
+    comments: usize,
+}
+
+impl<'a> Builder<'a> {
+    pub(crate) fn new(source: &'a str) -> Self {
+        Builder {
+            source,
+            children: Vec::new(),
+            pos: 0,
+            line: 1,
+            col: 1,
+            comments: 0,
+        }
+    }
+
+    /// Adds the whitespace and comments up to byte offset `end`. `comments`
+    /// are the spans of the comments that the lexer has skipped so far.
+    pub(crate) fn trivia(&mut self, end: usize, comments: &[Span]) {
+        while let Some(&comment) = comments.get(self.comments) {
+            if comment.start >= end {
+                break;
+            }
+            self.whitespace(comment.start);
+            self.push(TokenType::Comment, comment);
+            self.comments += 1;
+        }
+        self.whitespace(end);
+    }
+
+    fn whitespace(&mut self, end: usize) {
+        if end > self.pos {
+            let span = Span::new(self.pos, end, self.line, self.col);
+            self.push(TokenType::Whitespace, span);
+        }
+    }
+
+    /// Adds a token with the trivia before it.
+    pub(crate) fn token(&mut self, ty: TokenType, span: Span, comments: &[Span]) {
+        self.trivia(span.start, comments);
+        self.push(ty, span);
+    }
+
+    /// Adds the input at `span`, which the lexer skipped because of an
+    /// error, as an `Error` node.
+    pub(crate) fn error(&mut self, span: Span, comments: &[Span]) {
+        self.trivia(span.start, comments);
+        let checkpoint = self.checkpoint();
+        self.push(TokenType::Error, span);
+        self.finish_node(checkpoint, NodeKind::Error);
+    }
+
+    fn push(&mut self, ty: TokenType, span: Span) {
+        self.line = span.line;
+        self.col = span.col;
+        for ch in self.source[span.start..span.end].chars() {
+            if ch == '\n' {
+                self.line += 1;
+                self.col = 1;
+            } else {
+                self.col += 1;
+            }
+        }
+        self.pos = span.end;
+        self.children
+            .push(SyntaxElement::Token(SyntaxToken { ty, span }));
+    }
+
+    /// Start of a node whose first token has not been added yet. The trivia
+    /// before that token has to be added first.
+    pub(crate) fn checkpoint(&self) -> Checkpoint {
+        Checkpoint(self.children.len())
+    }
+
+    /// Makes the children that have been added since `checkpoint` a node.
+    /// Nothing is added if there are no such children.
+    pub(crate) fn finish_node(&mut self, checkpoint: Checkpoint, kind: NodeKind) {
+        let children = self.children.split_off(checkpoint.0);
+        let span = match (children.first(), children.last()) {
+            (Some(first), Some(last)) => first.span().to(last.span()),
+            _ => return,
+        };
+        self.children.push(SyntaxElement::Node(SyntaxNode {
+            kind,
+            span,
+            children,
+        }));
+    }
+
+    /// Replaces the last node by its children, for an identifier that turns
+    /// out to be a name and not an expression, like the function of a call.
+    pub(crate) fn unwrap_node(&mut self) {
+        match self.children.pop() {
+            Some(SyntaxElement::Node(node)) => self.children.extend(node.children),
+            other => self.children.extend(other),
+        }
+    }
+
+    /// The tree of the whole source, once it has been parsed.
+    pub(crate) fn finish(mut self, comments: &[Span]) -> SyntaxTree<'a> {
+        self.trivia(self.source.len(), comments);
+        let root = SyntaxNode {
+            kind: NodeKind::File,
+            span: Span::new(0, self.source.len(), 1, 1),
+            children: self.children,
+        };
+        SyntaxTree {
+            source: self.source,
+            root,
+        }
+    }
+}
+
+/// Turns a syntax tree back into the AST.
+struct Lowering<'a> {
+    source: &'a str,
+    // Whether `_FCT_ANON_ARGS` is used in the function that is being lowered.
+    uses_anon_args: bool,
+}
+
+type LResult<T> = Result<T, Diagnostic>;
+
+/// Error for a node that is not what the AST needs.
+fn malformed(node: &SyntaxNode) -> Diagnostic {
+    let message = match node.kind {
+        NodeKind::Error => "syntax error".to_string(),
+        kind => format!("malformed {:?} node", kind),
+    };
+    Diagnostic::syntax(message, node.span)
+}
+
+fn ident_token(node: &SyntaxNode) -> LResult<&SyntaxToken> {
+    node.token(TokenType::Ident).ok_or_else(|| malformed(node))
+}
+
+/// The operator of `node`, found with the `from_token` of its type.
+fn operator<T>(node: &SyntaxNode, from_token: fn(&TokenType) -> Option<T>) -> LResult<T> {
+    node.child_tokens()
+        .find_map(|token| from_token(&token.ty))
+        .ok_or_else(|| malformed(node))
+}
+
+impl<'a> Lowering<'a> {
+    fn text(&self, token: &SyntaxToken) -> &'a str {
+        token.text(self.source)
+    }
+
+    /// Text of a string or data token, without the quotes.
+    fn unquote(&self, token: &SyntaxToken) -> &'a str {
+        let text = self.text(token);
+        &text[1..text.len() - 1]
+    }
+
+    fn ident(&self, token: &SyntaxToken) -> Ident<&'a str> {
+        Ident {
+            name: self.text(token),
+            span: token.span,
+        }
+    }
+
+    fn statements(&mut self, node: &SyntaxNode) -> LResult<Vec<Statement<&'a str>>> {
+        node.child_nodes().map(|n| self.statement(n)).collect()
+    }
+
+    fn statement(&mut self, node: &SyntaxNode) -> LResult<Statement<&'a str>> {
+        let mut nodes = node.child_nodes();
+        let mut next = || nodes.next().ok_or_else(|| malformed(node));
+        let kind = match node.kind {
+            NodeKind::If => {
+                let cond = self.expression(next()?)?;
+                let if_block = self.statement(next()?)?;
+                let else_block = match next() {
+                    Ok(n) => Some(Box::new(self.statement(n)?)),
+                    Err(_) => None,
+                };
+                StatementKind::If {
+                    cond: Box::new(cond),
+                    if_block: Box::new(if_block),
+                    else_block,
+                }
+            }
+            NodeKind::While => StatementKind::While {
+                cond: Box::new(self.expression(next()?)?),
+                block: Box::new(self.statement(next()?)?),
+            },
+            NodeKind::For => {
+                // The parts of the header are told apart by the `;`s and the
+                // `)` before them.
+                let mut parts = [None, None, None];
+                let mut block = None;
+                let mut part = 0;
+                for child in &node.children {
+                    match child {
+                        SyntaxElement::Token(token)
+                            if token.ty == TokenType::SemiColon
+                                || token.ty == TokenType::Rparan =>
+                        {
+                            part += 1
+                        }
+                        SyntaxElement::Node(n) if part < 3 => {
+                            parts[part] = Some(Box::new(self.expression(n)?))
+                        }
+                        SyntaxElement::Node(n) => block = Some(self.statement(n)?),
+                        SyntaxElement::Token(_) => {}
+                    }
+                }
+                let [init_expr, cond, end_expr] = parts;
+                StatementKind::For {
+                    init_expr,
+                    cond,
+                    end_expr,
+                    block: Box::new(block.ok_or_else(|| malformed(node))?),
+                }
+            }
+            NodeKind::Foreach => StatementKind::Foreach {
+                var: self.ident(ident_token(node)?),
+                array: Box::new(self.expression(next()?)?),
+                block: Box::new(self.statement(next()?)?),
+            },
+            NodeKind::Repeat => StatementKind::Repeat {
+                block: Box::new(self.statement(next()?)?),
+                cond: Box::new(self.expression(next()?)?),
+            },
+            NodeKind::FunctionDef => {
+                let mut idents = node
+                    .child_tokens()
+                    .filter(|token| token.ty == TokenType::Ident)
+                    .map(|token| self.ident(token));
+                let ident = idents.next().ok_or_else(|| malformed(node))?;
+                let params = idents.collect();
+                let outer_uses_anon_args = std::mem::replace(&mut self.uses_anon_args, false);
+                let body = self.statement(next()?);
+                let uses_anon_args =
+                    std::mem::replace(&mut self.uses_anon_args, outer_uses_anon_args);
+                StatementKind::FunctionDef {
+                    ident,
+                    params,
+                    uses_anon_args,
+                    body: Box::new(body?),
+                }
+            }
+            NodeKind::Declaration => StatementKind::Declaration {
+                scope: match node.token(TokenType::GlobalVar) {
+                    Some(_) => VarScope::Global,
+                    None => VarScope::Local,
+                },
+                vars: node
+                    .child_nodes()
+                    .map(|n| self.var_decl(n))
+                    .collect::<LResult<_>>()?,
+            },
+            NodeKind::Include => {
+                let path = node
+                    .child_tokens()
+                    .find(|token| {
+                        token.ty == TokenType::ImpureStr || token.ty == TokenType::PureStr
+                    })
+                    .ok_or_else(|| malformed(node))?;
+                StatementKind::Include {
+                    path: self.unquote(path),
+                    path_span: path.span,
+                }
+            }
+            NodeKind::Return => StatementKind::Return {
+                expr: match next() {
+                    Ok(n) => Some(Box::new(self.expression(n)?)),
+                    Err(_) => None,
+                },
+            },
+            NodeKind::Break => StatementKind::Break,
+            NodeKind::Continue => StatementKind::Continue,
+            NodeKind::Empty => StatementKind::Empty,
+            NodeKind::Block => StatementKind::Block {
+                statements: self.statements(node)?,
+            },
+            NodeKind::ExpressionStatement => {
+                StatementKind::Expression(Box::new(self.expression(next()?)?))
+            }
+            NodeKind::Repeated => StatementKind::Repeated {
+                call: Box::new(self.expression(next()?)?),
+                count: Box::new(self.expression(next()?)?),
+            },
+            _ => return Err(malformed(node)),
+        };
+        Ok(Statement::new(kind, node.span))
+    }
+
+    fn var_decl(&mut self, node: &SyntaxNode) -> LResult<VarDecl<&'a str>> {
+        let init = match node.child_nodes().next() {
+            Some(n) => Some(Box::new(self.expression(n)?)),
+            None => None,
+        };
+        Ok(VarDecl {
+            ident: self.ident(ident_token(node)?),
+            init,
+        })
+    }
+
+    fn expression(&mut self, node: &SyntaxNode) -> LResult<Expression<&'a str>> {
+        let mut nodes = node.child_nodes();
+        let mut next = || nodes.next().ok_or_else(|| malformed(node));
+        let kind = match node.kind {
+            NodeKind::Identifier => {
+                let name = self.text(ident_token(node)?);
+                if name == "_FCT_ANON_ARGS" {
+                    self.uses_anon_args = true;
+                }
+                ExpressionKind::Identifier(name)
+            }
+            NodeKind::Literal => {
+                let token = node.child_tokens().next().ok_or_else(|| malformed(node))?;
+                match token.ty {
+                    TokenType::Num => {
+                        let literal = Token {
+                            literal: self.text(token),
+                            ty: token.ty,
+                            span: token.span,
+                        };
+                        let value = literal.num_value().map_err(|_| malformed(node))?;
+                        ExpressionKind::Number(value)
+                    }
+                    TokenType::ImpureStr => ExpressionKind::String(self.unquote(token)),
+                    TokenType::PureStr => ExpressionKind::Data(self.unquote(token)),
+                    TokenType::True => ExpressionKind::Boolean(true),
+                    TokenType::False => ExpressionKind::Boolean(false),
+                    TokenType::Null => ExpressionKind::Null,
+                    _ => return Err(malformed(node)),
+                }
+            }
+            NodeKind::Prefix => ExpressionKind::Prefix {
+                op: operator(node, UnaryOp::from_token)?,
+                expr: Box::new(self.expression(next()?)?),
+            },
+            NodeKind::Postfix => ExpressionKind::Postfix {
+                expr: Box::new(self.expression(next()?)?),
+                op: operator(node, UnaryOp::from_token)?,
+            },
+            NodeKind::Infix => ExpressionKind::Infix {
+                l_expr: Box::new(self.expression(next()?)?),
+                op: operator(node, BinaryOp::from_token)?,
+                r_expr: Box::new(self.expression(next()?)?),
+            },
+            NodeKind::Assign => ExpressionKind::Assign {
+                target: Box::new(self.expression(next()?)?),
+                op: operator(node, AssignOp::from_token)?,
+                expr: Box::new(self.expression(next()?)?),
+            },
+            NodeKind::Index => ExpressionKind::Index {
+                expr: Box::new(self.expression(next()?)?),
+                index: Box::new(self.expression(next()?)?),
+            },
+            NodeKind::Grouped => ExpressionKind::Grouped(Box::new(self.expression(next()?)?)),
+            NodeKind::Sequence => ExpressionKind::Sequence(
+                node.child_nodes()
+                    .map(|n| self.expression(n))
+                    .collect::<LResult<_>>()?,
+            ),
+            NodeKind::FunctionRef => ExpressionKind::FunctionRef(self.ident(ident_token(node)?)),
+            NodeKind::FunctionCall => {
+                let mut args = Vec::new();
+                for arg in node.child_nodes() {
+                    if arg.kind != NodeKind::NamedArg {
+                        args.push(Arg::Anon(self.expression(arg)?));
+                        continue;
+                    }
+                    let ident = self.ident(ident_token(arg)?);
+                    let value = arg.child_nodes().next().ok_or_else(|| malformed(arg))?;
+                    args.push(Arg::Named(ident, self.expression(value)?));
+                }
+                ExpressionKind::FunctionCall {
+                    ident: self.ident(ident_token(node)?),
+                    args,
+                }
+            }
+            _ => return Err(malformed(node)),
+        };
+        Ok(Expression::new(kind, node.span))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::parser::Parser;
+
+    #[test]
+    fn lossless() {
+        let code = "# head\ninclude ( 'a.inc' ) ;\r\n\
+            function f(a,b) {\tlocal_var c = -a [1], d;  # trailing\n\
+            for (;c<0x10;c++) foreach x (make_list(1, port : b, 'z')) {}\n\
+            repeat { c = (b) ** 2; } until c > 3 || ! _FCT_ANON_ARGS;\n\
+            return @f; }\n\
+            g(f(b:1,2)) x 3;\n  ";
+        let (tree, errors) = Parser::new(code).parse_cst();
+        assert_eq!(errors, vec![]);
+        assert_eq!(tree.text(), code);
+        assert_eq!(tree.to_ast(), Ok(Parser::new(code).parse().unwrap()));
+    }
+
+    #[test]
+    fn dump() {
+        let (tree, _) = Parser::new("x = (1); # one\n").parse_cst();
+        assert_eq!(
+            tree.dump(),
+            r##"File@0..15
+  ExpressionStatement@0..8
+    Assign@0..7
+      Identifier@0..1
+        Ident@0..1 "x"
+      Whitespace@1..2 " "
+      Assign@2..3 "="
+      Whitespace@3..4 " "
+      Grouped@4..7
+        Lparan@4..5 "("
+        Literal@5..6
+          Num@5..6 "1"
+        Rparan@6..7 ")"
+    SemiColon@7..8 ";"
+  Whitespace@8..9 " "
+  Comment@9..14 "# one"
+  Whitespace@14..15 "\n"
+"##
+        );
+    }
+
+    #[test]
+    fn spans_of_trivia() {
+        let code = "a;\n  # c\n b;";
+        let (tree, _) = Parser::new(code).parse_cst();
+        let trivia: Vec<_> = tree
+            .tokens()
+            .filter(|token| token.is_trivia())
+            .map(|token| (token.text(code), token.span.line, token.span.col))
+            .collect();
+        assert_eq!(trivia, [("\n  ", 1, 3), ("# c", 2, 3), ("\n ", 2, 6)]);
+    }
+
+    #[test]
+    fn syntax_errors() {
+        let code = "a = ;\nb = 1 ` 2;\n{ c(; d; } $ e;";
+        let (tree, errors) = Parser::new(code).parse_cst();
+        assert_eq!(errors.len(), 4, "{:?}", errors);
+        assert_eq!(tree.text(), code);
+        let kinds = |node: &SyntaxNode| -> Vec<NodeKind> {
+            node.child_nodes().map(|node| node.kind()).collect()
+        };
+        let root = tree.root();
+        assert_eq!(
+            kinds(root),
+            [
+                NodeKind::Error,
+                NodeKind::Error,
+                NodeKind::Block,
+                NodeKind::Error,
+                NodeKind::ExpressionStatement
+            ]
+        );
+        let block = root.child_nodes().nth(2).unwrap();
+        assert_eq!(
+            kinds(block),
+            [NodeKind::Error, NodeKind::ExpressionStatement]
+        );
+        // The lexer skips the characters that start no token.
+        let skipped: Vec<_> = tree
+            .tokens()
+            .filter(|token| token.ty == TokenType::Error)
+            .map(|token| token.text(code))
+            .collect();
+        assert_eq!(skipped, ["`", "$"]);
+
+        let error = tree.to_ast().unwrap_err();
+        assert_eq!(error.to_string(), "syntax error at 1:1");
+    }
+}
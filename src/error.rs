@@ -0,0 +1,68 @@
+use crate::span::Span;
+use std::error::Error;
+use std::fmt;
+use std::path::PathBuf;
+
+/// What a diagnostic is about.
+#[derive(Debug, PartialEq, Eq, Hash, Clone, Copy)]
+pub enum DiagnosticKind {
+    /// An error found while lexing or parsing the source code.
+    Syntax,
+    /// An error found while resolving the names used in the source code.
+    Resolve,
+    /// An error found while loading the files that a script includes.
+    Include,
+}
+
+/// An error in the source code, with its location.
+#[derive(Debug, PartialEq, Eq, Clone)]
+pub struct Diagnostic {
+    pub kind: DiagnosticKind,
+    pub message: String,
+    pub span: Span,
+    /// File in which the error was found, if it is not the file that was
+    /// checked but e.g. one that it includes.
+    pub path: Option<PathBuf>,
+}
+
+impl Diagnostic {
+    pub fn new<S: Into<String>>(kind: DiagnosticKind, message: S, span: Span) -> Self {
+        Diagnostic {
+            kind,
+            message: message.into(),
+            span,
+            path: None,
+        }
+    }
+
+    pub fn syntax<S: Into<String>>(message: S, span: Span) -> Self {
+        Diagnostic::new(DiagnosticKind::Syntax, message, span)
+    }
+
+    pub fn resolve<S: Into<String>>(message: S, span: Span) -> Self {
+        Diagnostic::new(DiagnosticKind::Resolve, message, span)
+    }
+
+    pub fn include<S: Into<String>, P: Into<PathBuf>>(message: S, path: P, span: Span) -> Self {
+        Diagnostic::new(DiagnosticKind::Include, message, span).in_file(path)
+    }
+
+    /// The same diagnostic, found in the file at `path`.
+    pub fn in_file<P: Into<PathBuf>>(self, path: P) -> Self {
+        Diagnostic {
+            path: Some(path.into()),
+            ..self
+        }
+    }
+}
+
+impl fmt::Display for Diagnostic {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        if let Some(path) = &self.path {
+            write!(f, "{}: ", path.display())?;
+        }
+        write!(f, "{} at {}", self.message, self.span)
+    }
+}
+
+impl Error for Diagnostic {}
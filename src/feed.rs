@@ -0,0 +1,29 @@
+//! Files of a feed, the directory tree of the plugins and include files.
+
+use std::fs;
+use std::io;
+use std::path::{Path, PathBuf};
+
+/// Adds `path` to `files` if it is a file, or the `.nasl` and `.inc` files
+/// below it, in sorted order, if it is a directory.
+pub fn collect_sources(path: &Path, files: &mut Vec<PathBuf>) -> io::Result<()> {
+    if !path.is_dir() {
+        files.push(path.to_path_buf());
+        return Ok(());
+    }
+    let mut entries = fs::read_dir(path)?
+        .map(|entry| entry.map(|entry| entry.path()))
+        .collect::<io::Result<Vec<_>>>()?;
+    entries.sort();
+    for entry in entries {
+        if entry.is_dir() {
+            collect_sources(&entry, files)?;
+        } else if entry
+            .extension()
+            .is_some_and(|ext| ext == "nasl" || ext == "inc")
+        {
+            files.push(entry);
+        }
+    }
+    Ok(())
+}
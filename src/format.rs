@@ -0,0 +1,113 @@
+//! Formatter of Nasl sources.
+//!
+//! The code is parsed and printed again by the unparser, so the layout is
+//! the canonical one described in the `unparse` module. What the tree does
+//! not know about is taken from the source: the comments, single empty
+//! lines between statements and how numbers and include paths are written.
+
+use crate::error::Diagnostic;
+use crate::parser::Parser;
+use crate::unparse::to_nasl_with_comments;
+
+/// Formats `source`, which has to be free of syntax errors.
+pub fn format(source: &str) -> Result<String, Vec<Diagnostic>> {
+    let mut parser = Parser::new(source);
+    let stmts = parser.parse()?;
+    Ok(to_nasl_with_comments(&stmts, source, parser.comments()))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn layout() {
+        let code = "if(a){b=c+1;}else{ d( port:80,1 ) ;}\nfunction f(x){return x*2;}";
+        let expected = "if (a) {\n  b = c + 1;\n} else {\n  d(port:80, 1);\n}\n\n\
+                        function f(x) {\n  return x * 2;\n}\n";
+        assert_eq!(format(code).unwrap(), expected);
+    }
+
+    #[test]
+    fn comments() {
+        let code = "# Header\n# of the plugin.\n\n\n\
+            include('a.inc'); # trailing   \n\
+            if (a) { # opens\n\
+            \x20 b = 0x1F;\n\n\n\
+            \x20 # before c\n\
+            \x20 c = 017; # after c\n\
+            \x20 # last\n\
+            }\n\
+            foreach x (l) {\n# only a comment\n}\n\
+            f(a, # inside\n  b);\n\
+            # end\n";
+        let expected = "# Header\n# of the plugin.\n\n\
+            include('a.inc'); # trailing\n\
+            if (a) {\n\
+            \x20 # opens\n\
+            \x20 b = 0x1F;\n\n\
+            \x20 # before c\n\
+            \x20 c = 017; # after c\n\
+            \x20 # last\n\
+            }\n\
+            foreach x (l) {\n  # only a comment\n}\n\
+            f(a, # inside\n\
+            \x20 b);\n\
+            # end\n";
+        let formatted = format(code).unwrap();
+        assert_eq!(formatted, expected);
+        assert_eq!(format(&formatted).unwrap(), formatted);
+    }
+
+    #[test]
+    fn bodies() {
+        let code = "if (a) b(); # b\nelse # else\n  c();\nwhile (x) # loop\n  x--;";
+        let expected = "if (a)\n  b(); # b\nelse\n  # else\n  c();\nwhile (x)\n  # loop\n  x--;\n";
+        assert_eq!(format(code).unwrap(), expected);
+    }
+
+    #[test]
+    fn comments_in_place() {
+        let cases = [
+            (
+                "if (a)\n  b;\n# between\nelse\n  c;\n",
+                "if (a)\n  b;\n# between\nelse\n  c;\n",
+            ),
+            (
+                "for (i = 0; # init\n i < 3; i++) {}\n",
+                "for (i = 0; # init\n  i < 3; i++) {}\n",
+            ),
+            (
+                "if (a) {\n  b;\n} # b\n# c\nelse {\n  c;\n}\n",
+                "if (a) {\n  b;\n} # b\n# c\nelse {\n  c;\n}\n",
+            ),
+            (
+                "repeat\n  a++;\n# until\nuntil a > 3;\n",
+                "repeat\n  a++;\n# until\nuntil a > 3;\n",
+            ),
+            ("x = a # a\n  + b;\n", "x = a # a\n  + b;\n"),
+            (
+                "if (a) { b(port: # port\n 80); }\n",
+                "if (a) {\n  b(port: # port\n    80);\n}\n",
+            ),
+            ("function # c\nf() {}\n", "function # c\n  f() {}\n"),
+            ("include( # c\n'a.inc');\n", "include( # c\n  'a.inc');\n"),
+            (
+                "while (1) { break # c\n; }\n",
+                "while (1) {\n  break # c\n    ;\n}\n",
+            ),
+            ("foreach # c\n v (l) {}\n", "foreach # c\n  v (l) {}\n"),
+            ("x = @ # c\nf;\n", "x = @ # c\n  f;\n"),
+        ];
+        for (code, expected) in &cases {
+            let formatted = format(code).unwrap();
+            assert_eq!(formatted, *expected, "{}", code);
+            assert_eq!(format(&formatted).unwrap(), formatted, "{}", code);
+        }
+    }
+
+    #[test]
+    fn syntax_errors() {
+        assert!(format("if (a").is_err());
+    }
+}
@@ -0,0 +1,307 @@
+//! Include dependency graph of a feed.
+//!
+//! The graph has a node for every `.nasl` and `.inc` file below a directory
+//! and for every file they include, and an edge from each file to each file
+//! it includes. Included files are found and parsed by an
+//! `IncludeResolver`, so they are read only once even if they are also part
+//! of the directory.
+//!
+//! The graph can be exported in the DOT format of Graphviz and as JSON. The
+//! JSON is an object with the list of the files, in the order of the nodes,
+//! and the names of the unused includes:
+//!
+//! ```json
+//! {"files": [{"name": "plugin.nasl", "includes": ["a.inc"], "closure": ["a.inc", "b.inc"]},
+//!            {"name": "a.inc", "includes": ["b.inc"], "closure": ["b.inc"]},
+//!            {"name": "b.inc", "includes": [], "closure": []},
+//!            {"name": "unused.inc", "includes": [], "closure": []}],
+//!  "unused": ["unused.inc"]}
+//! ```
+//!
+//! `closure` lists every file that a file includes directly or indirectly.
+
+use crate::error::Diagnostic;
+use crate::feed::collect_sources;
+use crate::include::{include_statements, FileId, IncludeResolver};
+use crate::parser::Parser;
+use std::collections::{BTreeSet, HashMap, HashSet, VecDeque};
+use std::fmt::Write;
+use std::fs;
+use std::io;
+use std::path::{Path, PathBuf};
+
+/// Index of a node in an `IncludeGraph`.
+#[derive(Debug, PartialEq, Eq, Hash, PartialOrd, Ord, Clone, Copy)]
+pub struct NodeId(u32);
+
+impl NodeId {
+    pub fn index(self) -> usize {
+        self.0 as usize
+    }
+}
+
+#[derive(Debug)]
+pub struct Node {
+    /// Path relative to the directory of the graph, with `/` between the
+    /// components, or the name of the include for files outside of it.
+    pub name: String,
+    pub path: PathBuf,
+    /// The files included by this one, in the order of the includes.
+    pub includes: Vec<NodeId>,
+}
+
+impl Node {
+    /// Whether this is an include file rather than a plugin.
+    pub fn is_include(&self) -> bool {
+        self.path.extension().is_some_and(|ext| ext == "inc")
+    }
+}
+
+#[derive(Debug, Default)]
+pub struct IncludeGraph {
+    nodes: Vec<Node>,
+    // Nodes by canonical path, so that a file is one node however its path
+    // is written.
+    by_path: HashMap<PathBuf, NodeId>,
+}
+
+impl IncludeGraph {
+    /// Builds the graph of the files below `dir`. The files they include
+    /// are looked up and loaded by `includes`. Includes that cannot be
+    /// loaded and syntax errors are returned together with the graph, the
+    /// graph just lacks the edges that are affected.
+    pub fn build(
+        dir: &Path,
+        includes: &mut IncludeResolver,
+    ) -> io::Result<(IncludeGraph, Vec<Diagnostic>)> {
+        let mut files = Vec::new();
+        collect_sources(dir, &mut files)?;
+
+        let mut graph = IncludeGraph::default();
+        let mut errors = Vec::new();
+        let mut queue = VecDeque::new();
+        for path in files {
+            let name = relative_name(dir, &path);
+            let id = graph.add_node(name.clone(), path)?;
+            // Include files that can be found are loaded as such, so that
+            // they are parsed only once.
+            let found = match includes.find(&name) {
+                Some(found) if graph.node(id).is_include() => Some(fs::canonicalize(found)?),
+                _ => None,
+            };
+            let file = match found {
+                Some(found) if graph.by_path.get(&found) == Some(&id) => {
+                    Some(includes.load(&name)?)
+                }
+                _ => None,
+            };
+            queue.push_back((id, file));
+        }
+
+        while let Some((id, file)) = queue.pop_front() {
+            let included: Vec<FileId> = match file {
+                Some(file) => {
+                    let file = includes.file(file);
+                    errors.extend(file.errors.iter().cloned());
+                    file.includes
+                        .iter()
+                        .map(|&(included, _)| included)
+                        .collect()
+                }
+                None => {
+                    let path = graph.node(id).path.clone();
+                    let source = String::from_utf8_lossy(&fs::read(&path)?).into_owned();
+                    let (stmts, syntax_errors) = Parser::new(&source).parse_recovering();
+                    errors.extend(syntax_errors.into_iter().map(|e| e.in_file(&path)));
+                    let mut included = Vec::new();
+                    for (name, span) in include_statements(&stmts) {
+                        match includes.load(&name) {
+                            Ok(file) => included.push(file),
+                            Err(e) => errors.push(Diagnostic::include(
+                                format!("cannot include `{}`: {}", name, e),
+                                &path,
+                                span,
+                            )),
+                        }
+                    }
+                    included
+                }
+            };
+
+            for file in included {
+                let loaded = includes.file(file);
+                let target = match graph.by_path.get(&fs::canonicalize(&loaded.path)?) {
+                    Some(&target) => target,
+                    None => {
+                        let target = graph.add_node(loaded.name.clone(), loaded.path.clone())?;
+                        queue.push_back((target, Some(file)));
+                        target
+                    }
+                };
+                let edges = &mut graph.nodes[id.index()].includes;
+                if !edges.contains(&target) {
+                    edges.push(target);
+                }
+            }
+        }
+        Ok((graph, errors))
+    }
+
+    fn add_node(&mut self, name: String, path: PathBuf) -> io::Result<NodeId> {
+        let id = NodeId(self.nodes.len() as u32);
+        self.by_path.insert(fs::canonicalize(&path)?, id);
+        self.nodes.push(Node {
+            name,
+            path,
+            includes: Vec::new(),
+        });
+        Ok(id)
+    }
+
+    pub fn nodes(&self) -> &[Node] {
+        &self.nodes
+    }
+
+    pub fn node(&self, id: NodeId) -> &Node {
+        &self.nodes[id.index()]
+    }
+
+    /// Node of the file called `name`.
+    pub fn find(&self, name: &str) -> Option<NodeId> {
+        self.nodes
+            .iter()
+            .position(|node| node.name == name)
+            .map(|i| NodeId(i as u32))
+    }
+
+    /// The files that `id` includes directly or indirectly, in the order of
+    /// the nodes. `id` itself is only part of it if it is in a cycle.
+    pub fn closure(&self, id: NodeId) -> Vec<NodeId> {
+        reachable(&[id], |id| &self.node(id).includes)
+    }
+
+    /// The files that include `id` directly or indirectly, in the order of
+    /// the nodes.
+    pub fn dependents(&self, id: NodeId) -> Vec<NodeId> {
+        let mut included_by = vec![Vec::new(); self.nodes.len()];
+        for (i, node) in self.nodes.iter().enumerate() {
+            for &target in &node.includes {
+                included_by[target.index()].push(NodeId(i as u32));
+            }
+        }
+        reachable(&[id], |id| &included_by[id.index()])
+    }
+
+    /// The include files that no plugin includes, directly or indirectly.
+    pub fn unused(&self) -> Vec<NodeId> {
+        let plugins: Vec<_> = (0..self.nodes.len())
+            .map(|i| NodeId(i as u32))
+            .filter(|&id| !self.node(id).is_include())
+            .collect();
+        let used: HashSet<_> = reachable(&plugins, |id| &self.node(id).includes)
+            .into_iter()
+            .collect();
+        (0..self.nodes.len())
+            .map(|i| NodeId(i as u32))
+            .filter(|id| self.node(*id).is_include() && !used.contains(id))
+            .collect()
+    }
+
+    /// The graph in the DOT format. Unused includes are dashed.
+    pub fn to_dot(&self) -> String {
+        let mut out = String::from("digraph includes {\n");
+        let unused: HashSet<_> = self.unused().into_iter().collect();
+        for (i, node) in self.nodes.iter().enumerate() {
+            let _ = write!(out, "  {}", dot_string(&node.name));
+            if unused.contains(&NodeId(i as u32)) {
+                out.push_str(" [style=dashed]");
+            }
+            out.push_str(";\n");
+        }
+        for node in &self.nodes {
+            for &target in &node.includes {
+                let _ = writeln!(
+                    out,
+                    "  {} -> {};",
+                    dot_string(&node.name),
+                    dot_string(&self.node(target).name)
+                );
+            }
+        }
+        out.push_str("}\n");
+        out
+    }
+
+    /// The graph as JSON, see the documentation of the module.
+    pub fn to_json(&self) -> String {
+        let names = |ids: &[NodeId]| {
+            let names: Vec<_> = ids
+                .iter()
+                .map(|&id| json_string(&self.node(id).name))
+                .collect();
+            format!("[{}]", names.join(", "))
+        };
+        let mut out = String::from("{\"files\": [");
+        for (i, node) in self.nodes.iter().enumerate() {
+            if i > 0 {
+                out.push(',');
+            }
+            let _ = write!(
+                out,
+                "\n  {{\"name\": {}, \"includes\": {}, \"closure\": {}}}",
+                json_string(&node.name),
+                names(&node.includes),
+                names(&self.closure(NodeId(i as u32)))
+            );
+        }
+        let _ = writeln!(out, "],\n \"unused\": {}}}", names(&self.unused()));
+        out
+    }
+}
+
+/// The nodes that can be reached from `start` by following one or more
+/// `edges`, in order.
+fn reachable<'g, F>(start: &[NodeId], edges: F) -> Vec<NodeId>
+where
+    F: Fn(NodeId) -> &'g [NodeId],
+{
+    let mut seen = BTreeSet::new();
+    let mut stack: Vec<NodeId> = start.iter().flat_map(|&id| edges(id)).copied().collect();
+    while let Some(next) = stack.pop() {
+        if seen.insert(next) {
+            stack.extend(edges(next));
+        }
+    }
+    seen.into_iter().collect()
+}
+
+/// `path` relative to `dir`, with `/` between the components.
+fn relative_name(dir: &Path, path: &Path) -> String {
+    let relative = path.strip_prefix(dir).unwrap_or(path);
+    let components: Vec<_> = relative
+        .components()
+        .map(|c| c.as_os_str().to_string_lossy())
+        .collect();
+    components.join("/")
+}
+
+fn dot_string(s: &str) -> String {
+    format!("\"{}\"", s.replace('\\', "\\\\").replace('"', "\\\""))
+}
+
+fn json_string(s: &str) -> String {
+    let mut out = String::from("\"");
+    for c in s.chars() {
+        match c {
+            '"' => out.push_str("\\\""),
+            '\\' => out.push_str("\\\\"),
+            '\n' => out.push_str("\\n"),
+            c if (c as u32) < 0x20 => {
+                let _ = write!(out, "\\u{:04x}", c as u32);
+            }
+            c => out.push(c),
+        }
+    }
+    out.push('"');
+    out
+}
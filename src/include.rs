@@ -0,0 +1,243 @@
+//! Loading of the files that scripts include.
+//!
+//! `include("http_func.inc");` makes the functions and global variables of
+//! `http_func.inc` available to a script. An `IncludeResolver` looks for
+//! such files in its search paths, in the order in which they were added,
+//! and keeps every file it loads, so a file that many scripts include is
+//! read and parsed only once:
+//!
+//! ```no_run
+//! use nasl_transpiler::include::IncludeResolver;
+//! use nasl_transpiler::parser::Parser;
+//! use nasl_transpiler::resolve::{resolve_with_includes, Builtins};
+//! use std::path::Path;
+//!
+//! let mut includes = IncludeResolver::new();
+//! includes.add_search_path("/var/lib/openvas/plugins");
+//!
+//! let code = std::fs::read_to_string("plugin.nasl").unwrap();
+//! let stmts = Parser::new(&code).parse().unwrap();
+//! let (symbols, errors) = includes.resolve_includes(Path::new("plugin.nasl"), &stmts);
+//! let (tree, undefined) = resolve_with_includes(&stmts, &Builtins::nasl(), &symbols);
+//! ```
+
+use crate::ast::{self, OwnedStatement, Statement, StatementKind};
+use crate::error::Diagnostic;
+use crate::parser::Parser;
+use crate::resolve::{resolve, Builtins, IncludedDef, SymbolTable};
+use crate::span::Span;
+use crate::visit::{walk_statement, walk_statements, Visitor};
+use std::collections::{HashMap, HashSet};
+use std::fs;
+use std::io;
+use std::path::{Path, PathBuf};
+use std::sync::Arc;
+
+/// Index of a file in an `IncludeResolver`.
+#[derive(Debug, PartialEq, Eq, Hash, PartialOrd, Ord, Clone, Copy)]
+pub struct FileId(u32);
+
+impl FileId {
+    pub fn index(self) -> usize {
+        self.0 as usize
+    }
+}
+
+/// A file that has been included.
+#[derive(Debug)]
+pub struct IncludedFile {
+    /// Name of the file as written in the include statements.
+    pub name: String,
+    pub path: PathBuf,
+    pub source: String,
+    pub statements: Vec<OwnedStatement>,
+    /// Files included by this one, with the span of the path of the include
+    /// statement.
+    pub includes: Vec<(FileId, Span)>,
+    /// Syntax errors of the file and includes that could not be loaded.
+    pub errors: Vec<Diagnostic>,
+    /// Functions and global variables defined by the file itself.
+    definitions: Vec<(Arc<str>, IncludedDef)>,
+}
+
+/// Finds, loads and caches included files.
+#[derive(Debug, Default)]
+pub struct IncludeResolver {
+    search_paths: Vec<PathBuf>,
+    files: Vec<IncludedFile>,
+    by_name: HashMap<String, FileId>,
+}
+
+impl IncludeResolver {
+    pub fn new() -> Self {
+        IncludeResolver::default()
+    }
+
+    /// Adds a directory to look for included files in, after the ones that
+    /// were added before.
+    pub fn add_search_path<P: Into<PathBuf>>(&mut self, path: P) {
+        self.search_paths.push(path.into());
+    }
+
+    pub fn file(&self, id: FileId) -> &IncludedFile {
+        &self.files[id.index()]
+    }
+
+    /// The files that have been loaded so far.
+    pub fn files(&self) -> &[IncludedFile] {
+        &self.files
+    }
+
+    /// Path of the included file `name`, from the first search path that
+    /// has it.
+    pub fn find(&self, name: &str) -> Option<PathBuf> {
+        self.search_paths
+            .iter()
+            .map(|dir| dir.join(name))
+            .find(|path| path.is_file())
+    }
+
+    /// Loads the included file `name` and the files it includes, unless
+    /// they have been loaded already.
+    pub fn load(&mut self, name: &str) -> io::Result<FileId> {
+        if let Some(id) = self.by_name.get(name) {
+            return Ok(*id);
+        }
+        let path = self.find(name).ok_or_else(|| {
+            io::Error::new(io::ErrorKind::NotFound, "not found in the search paths")
+        })?;
+        // Feed files are not necessarily UTF-8.
+        let source = String::from_utf8_lossy(&fs::read(&path)?).into_owned();
+
+        let id = FileId(self.files.len() as u32);
+        let (stmts, syntax_errors) = Parser::new(&source).parse_recovering();
+        let (tree, _) = resolve(&stmts, &Builtins::new());
+        let definitions = tree
+            .definitions()
+            .iter()
+            .filter(|def| def.scope == tree.global())
+            .map(|def| {
+                let included = IncludedDef {
+                    file: id,
+                    kind: def.kind,
+                    span: def.span,
+                };
+                (Arc::from(def.name), included)
+            })
+            .collect();
+        let mut errors: Vec<_> = syntax_errors
+            .into_iter()
+            .map(|e| e.in_file(&path))
+            .collect();
+        let included = include_statements(&stmts);
+        let statements = ast::into_owned(stmts);
+
+        // Registered before the includes are loaded, so that a cycle ends
+        // here.
+        self.by_name.insert(name.to_string(), id);
+        self.files.push(IncludedFile {
+            name: name.to_string(),
+            path: path.clone(),
+            source,
+            statements,
+            includes: Vec::new(),
+            errors: Vec::new(),
+            definitions,
+        });
+
+        let mut includes = Vec::new();
+        for (name, span) in included {
+            match self.load(&name) {
+                Ok(included) => includes.push((included, span)),
+                Err(e) => errors.push(include_error(&name, e, &path, span)),
+            }
+        }
+        let file = &mut self.files[id.index()];
+        file.includes = includes;
+        file.errors = errors;
+        Ok(id)
+    }
+
+    /// Loads the files that `stmts`, the statements of the file at `path`,
+    /// include directly or indirectly. Returns the definitions of these
+    /// files, where the first definition of a name wins, together with the
+    /// errors of the files and the include cycles among them.
+    pub fn resolve_includes<S: AsRef<str>>(
+        &mut self,
+        path: &Path,
+        stmts: &[Statement<S>],
+    ) -> (SymbolTable, Vec<Diagnostic>) {
+        let mut errors = Vec::new();
+        let mut included = Vec::new();
+        for (name, span) in include_statements(stmts) {
+            match self.load(&name) {
+                Ok(id) => included.push(id),
+                Err(e) => errors.push(include_error(&name, e, path, span)),
+            }
+        }
+
+        let mut symbols = SymbolTable::new();
+        let mut visited = HashSet::new();
+        for id in included {
+            if visited.insert(id) {
+                self.merge(id, &mut symbols, &mut errors, &mut visited, &mut Vec::new());
+            }
+        }
+        (symbols, errors)
+    }
+
+    /// Adds the definitions and errors of `id` and the files it includes.
+    /// `stack` holds the files that include `id`, to find cycles.
+    fn merge(
+        &self,
+        id: FileId,
+        symbols: &mut SymbolTable,
+        errors: &mut Vec<Diagnostic>,
+        visited: &mut HashSet<FileId>,
+        stack: &mut Vec<FileId>,
+    ) {
+        let file = self.file(id);
+        for (name, def) in &file.definitions {
+            symbols.insert(name.clone(), *def);
+        }
+        errors.extend(file.errors.iter().cloned());
+
+        stack.push(id);
+        for &(included, span) in &file.includes {
+            if let Some(start) = stack.iter().position(|&f| f == included) {
+                let cycle: Vec<_> = stack[start..]
+                    .iter()
+                    .chain(Some(&included))
+                    .map(|&f| self.file(f).name.as_str())
+                    .collect();
+                let message = format!("include cycle {}", cycle.join(" -> "));
+                errors.push(Diagnostic::include(message, &file.path, span));
+            } else if visited.insert(included) {
+                self.merge(included, symbols, errors, visited, stack);
+            }
+        }
+        stack.pop();
+    }
+}
+
+fn include_error(name: &str, e: io::Error, path: &Path, span: Span) -> Diagnostic {
+    Diagnostic::include(format!("cannot include `{}`: {}", name, e), path, span)
+}
+
+/// The paths of the include statements in `stmts` with their spans.
+pub fn include_statements<S: AsRef<str>>(stmts: &[Statement<S>]) -> Vec<(String, Span)> {
+    let mut includes = Includes(Vec::new());
+    walk_statements(&mut includes, stmts);
+    includes.0
+}
+
+struct Includes(Vec<(String, Span)>);
+
+impl<'ast, S: AsRef<str>> Visitor<'ast, S> for Includes {
+    fn visit_statement(&mut self, stmt: &'ast Statement<S>) {
+        if let StatementKind::Include { path, path_span } = &stmt.kind {
+            self.0.push((path.as_ref().to_string(), *path_span));
+        }
+        walk_statement(self, stmt);
+    }
+}
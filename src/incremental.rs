@@ -0,0 +1,377 @@
+//! Incremental reparsing.
+//!
+//! A `ParsedFile` keeps a source together with its statements and syntax
+//! errors. When the source is edited only the top level statements around
+//! the edit are parsed again, the statements before them and after them are
+//! kept and only have their spans moved:
+//!
+//! ```
+//! use nasl_transpiler::incremental::{ParsedFile, TextEdit};
+//!
+//! let mut file = ParsedFile::parse("a = 1;\nb = 2;\nc = 3;\n".to_string());
+//! let reuse = file.edit(&TextEdit::new(11..12, "20"));
+//! assert_eq!(file.source(), "a = 1;\nb = 20;\nc = 3;\n");
+//! assert_eq!((reuse.reused, reuse.reparsed), (2, 1));
+//! ```
+//!
+//! The parser starts again right after the last statement before the edit,
+//! or one statement earlier if an `else` could attach to that one. It stops
+//! as soon as it gets to the start of an old statement after the edit: the
+//! rest of the source did not change, so parsing it again would give the old
+//! statements. The result is the same as parsing the whole new source.
+
+use crate::ast::{self, Expression, Ident, OwnedStatement, Statement, StatementKind};
+use crate::error::Diagnostic;
+use crate::parser::Parser;
+use crate::span::Span;
+use crate::visit::{walk_expression_mut, walk_statement_mut, walk_statements_mut, VisitorMut};
+use std::ops::Range;
+use std::sync::Arc;
+
+/// Replacement of the bytes in `range` of a source with `text`.
+#[derive(Debug, PartialEq, Eq, Clone)]
+pub struct TextEdit {
+    pub range: Range<usize>,
+    pub text: String,
+}
+
+impl TextEdit {
+    pub fn new<S: Into<String>>(range: Range<usize>, text: S) -> Self {
+        TextEdit {
+            range,
+            text: text.into(),
+        }
+    }
+}
+
+/// How many top level statements an edit kept and how many it parsed again.
+#[derive(Debug, PartialEq, Eq, Clone, Copy)]
+pub struct Reuse {
+    pub reused: usize,
+    pub reparsed: usize,
+}
+
+/// A source with the result of parsing it, which can be edited.
+#[derive(Debug, Clone)]
+pub struct ParsedFile {
+    source: String,
+    statements: Vec<OwnedStatement>,
+    errors: Vec<Diagnostic>,
+}
+
+impl ParsedFile {
+    /// Parses the whole `source`, recovering from errors.
+    pub fn parse(source: String) -> Self {
+        let (stmts, errors) = Parser::new(&source).parse_recovering();
+        let statements = ast::into_owned(stmts);
+        ParsedFile {
+            source,
+            statements,
+            errors,
+        }
+    }
+
+    pub fn source(&self) -> &str {
+        &self.source
+    }
+
+    pub fn statements(&self) -> &[OwnedStatement] {
+        &self.statements
+    }
+
+    pub fn errors(&self) -> &[Diagnostic] {
+        &self.errors
+    }
+
+    /// Applies `edit` to the source and updates the statements and errors.
+    ///
+    /// # Panics
+    ///
+    /// Panics if the range of the edit is out of bounds or does not lie on
+    /// character boundaries, like `String::replace_range`.
+    pub fn edit(&mut self, edit: &TextEdit) -> Reuse {
+        let Range { start, end } = edit.range;
+        assert!(
+            start <= end
+                && self.source.is_char_boundary(start)
+                && self.source.is_char_boundary(end),
+            "invalid edit range {:?}",
+            edit.range
+        );
+
+        // The first statement that touches the edit is parsed again, as is
+        // the one before it if an `else` in the edit could belong to it.
+        let first = self.statements.partition_point(|s| s.span.end < start);
+        let keep = match first.checked_sub(1) {
+            Some(prev) if self.statements[prev].ends_with_open_if() => prev,
+            _ => first,
+        };
+        let (window, line, col) = match keep.checked_sub(1) {
+            Some(prev) => {
+                let span = self.statements[prev].span;
+                let (line, col) = advance(span.line, span.col, &self.source[span.start..span.end]);
+                (span.end, line, col)
+            }
+            None => (0, 1, 1),
+        };
+
+        // Old statements after the edit, where the parser can stop.
+        let after = first + self.statements[first..].partition_point(|s| s.span.start < end);
+        let inserted = edit.text.len() as isize - (end - start) as isize;
+        let sync: Vec<usize> = self.statements[after..]
+            .iter()
+            .map(|s| (s.span.start as isize + inserted) as usize)
+            .collect();
+
+        let old_end = advance(line, col, &self.source[window..end]);
+        self.source.replace_range(start..end, &edit.text);
+        let new_end = advance(line, col, &self.source[window..start + edit.text.len()]);
+
+        let (stmts, errors, stopped) = Parser::starting_at(&self.source, window, line, col)
+            .parse_recovering_until(|pos| sync.binary_search(&pos).is_ok());
+        let reparsed = ast::into_owned(stmts);
+        let count = reparsed.len();
+
+        let mut shift = Shift {
+            offset: inserted,
+            lines: new_end.0 as isize - old_end.0 as isize,
+            cols: new_end.1 as isize - old_end.1 as isize,
+            line: old_end.0,
+        };
+        let (mut tail, tail_errors) = match stopped {
+            Some(pos) => {
+                let index = after + sync.binary_search(&pos).unwrap();
+                let old_start = self.statements[index].span.start;
+                let tail_errors = self
+                    .errors
+                    .iter()
+                    .filter(|e| e.span.start >= old_start)
+                    .map(|e| Diagnostic {
+                        span: shift.span(e.span),
+                        ..e.clone()
+                    })
+                    .collect();
+                (self.statements.split_off(index), tail_errors)
+            }
+            None => (Vec::new(), Vec::new()),
+        };
+        walk_statements_mut(&mut shift, &mut tail);
+
+        self.statements.truncate(keep);
+        self.statements.extend(reparsed);
+        self.statements.append(&mut tail);
+        self.errors.retain(|e| e.span.start < window);
+        self.errors.extend(errors);
+        self.errors.extend(tail_errors);
+
+        Reuse {
+            reused: self.statements.len() - count,
+            reparsed: count,
+        }
+    }
+}
+
+/// Line and column after `text`, which starts at `line` and `col`.
+fn advance(mut line: usize, mut col: usize, text: &str) -> (usize, usize) {
+    for c in text.chars() {
+        if c == '\n' {
+            line += 1;
+            col = 1;
+        } else {
+            col += 1;
+        }
+    }
+    (line, col)
+}
+
+/// Moves the spans of the nodes after an edit. The spans on `line`, the
+/// line where the edit ended, move by `cols` columns as well.
+struct Shift {
+    offset: isize,
+    lines: isize,
+    cols: isize,
+    line: usize,
+}
+
+impl Shift {
+    fn span(&self, span: Span) -> Span {
+        let col = if span.line == self.line {
+            (span.col as isize + self.cols) as usize
+        } else {
+            span.col
+        };
+        Span {
+            start: (span.start as isize + self.offset) as usize,
+            end: (span.end as isize + self.offset) as usize,
+            line: (span.line as isize + self.lines) as usize,
+            col,
+        }
+    }
+}
+
+impl VisitorMut<Arc<str>> for Shift {
+    fn visit_statement_mut(&mut self, stmt: &mut Statement<Arc<str>>) {
+        stmt.span = self.span(stmt.span);
+        if let StatementKind::Include { path_span, .. } = &mut stmt.kind {
+            *path_span = self.span(*path_span);
+        }
+        walk_statement_mut(self, stmt);
+    }
+
+    fn visit_expression_mut(&mut self, expr: &mut Expression<Arc<str>>) {
+        expr.span = self.span(expr.span);
+        walk_expression_mut(self, expr);
+    }
+
+    fn visit_ident_mut(&mut self, ident: &mut Ident<Arc<str>>) {
+        ident.span = self.span(ident.span);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Applies `edits` one after the other and checks the result against
+    /// parsing the whole source. Returns the reuse of the last edit.
+    fn check(source: &str, edits: &[TextEdit]) -> Reuse {
+        let mut file = ParsedFile::parse(source.to_string());
+        let mut reuse = None;
+        for edit in edits {
+            reuse = Some(file.edit(edit));
+            let full = ParsedFile::parse(file.source().to_string());
+            assert_eq!(file.statements(), full.statements(), "{:?}", file.source());
+            assert_eq!(file.errors(), full.errors(), "{:?}", file.source());
+        }
+        reuse.unwrap()
+    }
+
+    const FUNCTIONS: &str = "function a() {\n  return 1;\n}\n\n\
+        function b(x) {\n  return x + 1;\n}\n\n\
+        # c\nfunction c() { return b(x:a()); }\n";
+
+    #[test]
+    fn reuses_unchanged_statements() {
+        let body = FUNCTIONS.find("x + 1").unwrap();
+        let reuse = check(FUNCTIONS, &[TextEdit::new(body..body + 1, "(x * 2)\n")]);
+        assert_eq!(
+            reuse,
+            Reuse {
+                reused: 2,
+                reparsed: 1
+            }
+        );
+
+        let reuse = check(FUNCTIONS, &[TextEdit::new(0..0, "x = 1; ")]);
+        assert_eq!(
+            reuse,
+            Reuse {
+                reused: 3,
+                reparsed: 1
+            }
+        );
+
+        let len = FUNCTIONS.len();
+        let reuse = check(FUNCTIONS, &[TextEdit::new(len..len, "d();")]);
+        assert_eq!(
+            reuse,
+            Reuse {
+                reused: 3,
+                reparsed: 1
+            }
+        );
+    }
+
+    #[test]
+    fn moves_spans() {
+        let code = "a = 1; b = [1, 2];\n\ninclude('x.inc');";
+        check(
+            code,
+            &[
+                TextEdit::new(4..5, "100"),
+                TextEdit::new(0..0, "\n\n"),
+                TextEdit::new(2..6, "z\n=\n"),
+                TextEdit::new(0..4, ""),
+            ],
+        );
+    }
+
+    #[test]
+    fn edits_changing_other_statements() {
+        // An `else` belongs to the `if` before it.
+        let code = "if (a) b();\nc();\nd();";
+        let reuse = check(code, &[TextEdit::new(12..12, "else ")]);
+        assert_eq!(
+            reuse,
+            Reuse {
+                reused: 1,
+                reparsed: 1
+            }
+        );
+        // A string or a comment that is not closed swallows the rest.
+        check(code, &[TextEdit::new(12..12, "'")]);
+        check(code, &[TextEdit::new(12..12, "# ")]);
+        // Statements that are merged or split.
+        check(code, &[TextEdit::new(15..17, "")]);
+        check(code, &[TextEdit::new(13..13, ";\n")]);
+        check("abc = 1;", &[TextEdit::new(1..1, " = 2; ")]);
+    }
+
+    #[test]
+    fn syntax_errors() {
+        let code = "a = ;\nb = 1;\nc = );\nd = 2;";
+        check(
+            code,
+            &[
+                TextEdit::new(4..4, "0"),
+                TextEdit::new(0..0, "if ("),
+                TextEdit::new(0..4, "{"),
+                TextEdit::new(0..1, "$"),
+            ],
+        );
+    }
+
+    #[test]
+    fn random_edits() {
+        const SNIPPETS: &[&str] = &[
+            "",
+            "x",
+            "1",
+            ";",
+            "{",
+            "}",
+            "(",
+            ")",
+            "else ",
+            "if (a) ",
+            "\n",
+            "#",
+            "'",
+            "\"",
+            " = ",
+            "f(a:1);",
+            "function g() {",
+            "é",
+        ];
+        let mut file = ParsedFile::parse(FUNCTIONS.to_string());
+        let mut seed: u64 = 47;
+        let mut random = |n: usize| {
+            seed = seed
+                .wrapping_mul(6364136223846793005)
+                .wrapping_add(1442695040888963407);
+            (seed >> 33) as usize % n
+        };
+        for _ in 0..500 {
+            let source = file.source();
+            let boundary = |pos: usize| (pos..=source.len()).find(|&p| source.is_char_boundary(p));
+            let start = boundary(random(source.len() + 1)).unwrap();
+            let end = boundary(start + random(4)).unwrap_or(start);
+            let text = SNIPPETS[random(SNIPPETS.len())];
+            file.edit(&TextEdit::new(start..end, text));
+
+            let full = ParsedFile::parse(file.source().to_string());
+            assert_eq!(file.statements(), full.statements(), "{:?}", file.source());
+            assert_eq!(file.errors(), full.errors(), "{:?}", file.source());
+        }
+    }
+}
@@ -0,0 +1,86 @@
+//! Interning of the strings of the Nasl sources.
+
+use std::collections::HashMap;
+use std::sync::Arc;
+
+/// Handle of a string stored in an `Interner`.
+///
+/// Symbols are only meaningful together with the interner that created
+/// them. Equal strings get the same symbol, so comparing symbols compares
+/// the strings.
+#[derive(Debug, PartialEq, Eq, Hash, PartialOrd, Ord, Clone, Copy)]
+pub struct Symbol(u32);
+
+impl Symbol {
+    pub fn index(self) -> usize {
+        self.0 as usize
+    }
+}
+
+/// Stores each distinct string once.
+///
+/// An interner can be shared by any number of files, e.g. by all plugins
+/// of a feed, so names like `port` or `get_kb_item` are kept only once.
+#[derive(Debug, Default)]
+pub struct Interner {
+    symbols: HashMap<Arc<str>, Symbol>,
+    strings: Vec<Arc<str>>,
+}
+
+impl Interner {
+    pub fn new() -> Self {
+        Interner::default()
+    }
+
+    /// Symbol of `s`, which is added to the interner if it is new.
+    pub fn intern(&mut self, s: &str) -> Symbol {
+        if let Some(symbol) = self.symbols.get(s) {
+            return *symbol;
+        }
+        let symbol = Symbol(self.strings.len() as u32);
+        let s: Arc<str> = Arc::from(s);
+        self.strings.push(s.clone());
+        self.symbols.insert(s, symbol);
+        symbol
+    }
+
+    /// Symbol of `s` if it has been interned already.
+    pub fn get(&self, s: &str) -> Option<Symbol> {
+        self.symbols.get(s).copied()
+    }
+
+    /// The string of `symbol`.
+    ///
+    /// Panics if `symbol` was created by another interner.
+    pub fn resolve(&self, symbol: Symbol) -> &str {
+        &self.strings[symbol.index()]
+    }
+
+    /// Number of distinct strings.
+    pub fn len(&self) -> usize {
+        self.strings.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.strings.is_empty()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn interning() {
+        let mut interner = Interner::new();
+        let port = interner.intern("port");
+        let soc = interner.intern("soc");
+        assert_ne!(port, soc);
+        assert_eq!(interner.intern("port"), port);
+        assert_eq!(interner.get("soc"), Some(soc));
+        assert_eq!(interner.get("data"), None);
+        assert_eq!(interner.resolve(port), "port");
+        assert_eq!(interner.resolve(soc), "soc");
+        assert_eq!(interner.len(), 2);
+    }
+}
@@ -5,7 +5,9 @@
 //! that the original interpreter uses Latin encoding so
 //! we don't need to pay for unicode.
 
-use crate::token::Token;
+use crate::error::Diagnostic;
+use crate::span::Span;
+use crate::token::{Token, TokenType};
 use std::str::Chars;
 
 type LResult<T> = Result<T, String>;
@@ -21,191 +23,229 @@ pub struct Lexer<'a> {
     cur_pos: usize,
     cur_char: Option<char>,
     peek_char: Option<char>,
+
+    // Location of `cur_char`, starting from 1.
+    line: usize,
+    col: usize,
+
+    comments: Vec<Span>,
 }
 
 impl<'a> Lexer<'a> {
     pub fn new(data: &'a str) -> Self {
-        let mut peek_cursor = data.chars();
-        peek_cursor.next();
-        peek_cursor.next();
+        Lexer::starting_at(data, 0, 1, 1)
+    }
+
+    /// Lexer that starts at byte offset `pos` of `data`, which is at `line`
+    /// and `col`, e.g. to lex a part of a file again.
+    pub(crate) fn starting_at(data: &'a str, pos: usize, line: usize, col: usize) -> Self {
+        let mut peek_cursor = data[pos..].chars();
+        let cur_char = peek_cursor.next();
+        let peek_char = peek_cursor.next();
         Lexer {
             data,
             peek_cursor,
-            cur_pos: 0,
-            cur_char: data.chars().next(),
-            peek_char: data.chars().skip(1).next(),
+            cur_pos: pos,
+            cur_char,
+            peek_char,
+            line,
+            col,
+            comments: Vec::new(),
         }
     }
 
-    pub fn next_token(&mut self) -> LResult<Token<'a>> {
+    /// Spans of the comments skipped so far, from the `#` up to the end of
+    /// the line.
+    pub fn comments(&self) -> &[Span] {
+        &self.comments
+    }
+
+    /// Returns the next token. On error, the erroneous input is skipped
+    /// so that calling `next_token` again continues after it.
+    pub fn next_token(&mut self) -> Result<Token<'a>, Diagnostic> {
         self.eat_whitespace_or_comment();
-        let token = match self.cur_char {
-            Some('(') => Token::Lparan,
-            Some(')') => Token::Rparan,
-            Some('{') => Token::Lbrace,
-            Some('}') => Token::Rbrace,
-            Some('[') => Token::Lbracket,
-            Some(']') => Token::Rbracket,
-            Some(';') => Token::SemiColon,
-            Some(':') => Token::Colon,
-            Some('"') => Token::ImpureStr(self.read_impure_str()?),
-            Some('\'') => Token::PureStr(self.read_pure_str()?),
-            Some(',') => Token::Comma,
+        let (start_pos, line, col) = (self.cur_pos, self.line, self.col);
+        let result = self.read_token();
+        let span = Span::new(start_pos, self.cur_pos, line, col);
+        match result {
+            Ok(token) => Ok(Token { span, ..token }),
+            Err(message) => Err(Diagnostic::syntax(message, span)),
+        }
+    }
+
+    fn read_token(&mut self) -> LResult<Token<'a>> {
+        let start_pos = self.cur_pos;
+        let ty = match self.cur_char {
+            Some('(') => TokenType::Lparan,
+            Some(')') => TokenType::Rparan,
+            Some('{') => TokenType::Lbrace,
+            Some('}') => TokenType::Rbrace,
+            Some('[') => TokenType::Lbracket,
+            Some(']') => TokenType::Rbracket,
+            Some(';') => TokenType::SemiColon,
+            Some(':') => TokenType::Colon,
+            Some('~') => TokenType::BwNot,
+            Some('@') => TokenType::At,
+            Some('"') => return Ok(Token::new(TokenType::ImpureStr, self.read_impure_str()?)),
+            Some('\'') => return Ok(Token::new(TokenType::PureStr, self.read_pure_str()?)),
+            Some(',') => TokenType::Comma,
             Some('+') => {
-                let token = match self.peek_char {
-                    Some('+') => Token::Incr,
-                    Some('=') => Token::PlusEq,
-                    _ => Token::Plus,
+                let ty = match self.peek_char {
+                    Some('+') => TokenType::Incr,
+                    Some('=') => TokenType::PlusEq,
+                    _ => TokenType::Plus,
                 };
-                if token != Token::Plus {
+                if ty != TokenType::Plus {
                     let _ = self.read_char();
                 }
-                token
+                ty
             }
             Some('-') => {
-                let token = match self.peek_char {
-                    Some('-') => Token::Decr,
-                    Some('=') => Token::MinusEq,
-                    _ => Token::Minus,
+                let ty = match self.peek_char {
+                    Some('-') => TokenType::Decr,
+                    Some('=') => TokenType::MinusEq,
+                    _ => TokenType::Minus,
                 };
-                if token != Token::Minus {
+                if ty != TokenType::Minus {
                     let _ = self.read_char();
                 }
-                token
+                ty
             }
             Some('*') => {
-                let token = match self.peek_char {
-                    Some('*') => Token::Pow,
-                    Some('=') => Token::MulEq,
-                    _ => Token::Mul,
+                let ty = match self.peek_char {
+                    Some('*') => TokenType::Pow,
+                    Some('=') => TokenType::MulEq,
+                    _ => TokenType::Mul,
                 };
-                if token != Token::Mul {
+                if ty != TokenType::Mul {
                     let _ = self.read_char();
                 }
-                token
+                ty
             }
             Some('/') => {
-                let token = match self.peek_char {
-                    Some('=') => Token::DivEq,
-                    _ => Token::Div,
+                let ty = match self.peek_char {
+                    Some('=') => TokenType::DivEq,
+                    _ => TokenType::Div,
                 };
-                if token != Token::Div {
+                if ty != TokenType::Div {
                     let _ = self.read_char();
                 }
-                token
+                ty
             }
             Some('%') => {
-                let token = match self.peek_char {
-                    Some('=') => Token::ModEq,
-                    _ => Token::Mod,
+                let ty = match self.peek_char {
+                    Some('=') => TokenType::ModEq,
+                    _ => TokenType::Mod,
                 };
-                if token != Token::Mod {
+                if ty != TokenType::Mod {
                     let _ = self.read_char();
                 }
-                token
+                ty
             }
             Some('=') => {
-                let token = match self.peek_char {
-                    Some('=') => Token::Equ,
-                    Some('~') => Token::ReMatch,
-                    _ => Token::Assign,
+                let ty = match self.peek_char {
+                    Some('=') => TokenType::Equ,
+                    Some('~') => TokenType::ReMatch,
+                    _ => TokenType::Assign,
                 };
-                if token != Token::Assign {
+                if ty != TokenType::Assign {
                     let _ = self.read_char();
                 }
-                token
+                ty
             }
             Some('!') => {
-                let token = match self.peek_char {
-                    Some('=') => Token::Nequ,
-                    Some('~') => Token::NreMatch,
-                    _ => Token::Not,
+                let ty = match self.peek_char {
+                    Some('=') => TokenType::Nequ,
+                    Some('~') => TokenType::NreMatch,
+                    _ => TokenType::Not,
                 };
-                if token != Token::Not {
+                if ty != TokenType::Not {
                     let _ = self.read_char();
                 }
-                token
+                ty
             }
             Some('&') => {
                 if self.peek_char == Some('&') {
                     let _ = self.read_char();
-                    Token::And
+                    TokenType::And
                 } else {
-                    Token::BwAnd
+                    TokenType::BwAnd
                 }
             }
             Some('|') => {
                 if self.peek_char == Some('|') {
                     let _ = self.read_char();
-                    Token::Or
+                    TokenType::Or
                 } else {
-                    Token::BwOr
+                    TokenType::BwOr
                 }
             }
             Some('^') => {
                 if self.peek_char == Some('=') {
                     let _ = self.read_char();
-                    Token::XorEq
+                    TokenType::XorEq
                 } else {
-                    Token::BwXor
+                    TokenType::BwXor
                 }
             }
-            Some('>') => {
-                let token = match self.peek_char {
-                    Some('<') => {
-                        let _ = self.read_char();
-                        Token::Substr
-                    }
-                    Some('=') => {
-                        let _ = self.read_char();
-                        Token::Gte
-                    }
-                    Some('>') => {
-                        let _ = self.read_char();
-                        let tok = match self.peek_char {
-                            Some('=') => Token::ShrEq,
-                            Some('>') => Token::Ushr,
-                            _ => Token::Shr,
-                        };
-                        if tok != Token::Shr {
+            Some('>') => match self.peek_char {
+                Some('<') => {
+                    let _ = self.read_char();
+                    TokenType::Substr
+                }
+                Some('=') => {
+                    let _ = self.read_char();
+                    TokenType::Gte
+                }
+                Some('>') => {
+                    let _ = self.read_char();
+                    match self.peek_char {
+                        Some('=') => {
                             let _ = self.read_char();
+                            TokenType::ShrEq
                         }
-                        tok
-                    }
-                    Some('!') => {
-                        let _ = self.read_char();
-                        let tok = if self.peek_char == Some('<') {
+                        Some('>') => {
                             let _ = self.read_char();
-                            Token::NSUBSTR
-                        } else {
-                            return Err(format!("Expected '<', got {:?}", self.peek_char));
-                        };
+                            if self.peek_char == Some('=') {
+                                let _ = self.read_char();
+                                TokenType::UshrEq
+                            } else {
+                                TokenType::Ushr
+                            }
+                        }
+                        _ => TokenType::Shr,
+                    }
+                }
+                Some('!') => {
+                    let _ = self.read_char();
+                    if self.peek_char == Some('<') {
                         let _ = self.read_char();
-                        tok
+                        TokenType::NSUBSTR
+                    } else {
+                        return Err(format!("Expected '<', got {:?}", self.peek_char));
                     }
-                    _ => Token::Gt,
-                };
-                token
-            }
+                }
+                _ => TokenType::Gt,
+            },
             Some('<') => {
-                let token = match self.peek_char {
-                    Some('=') => Token::Lte,
+                let ty = match self.peek_char {
+                    Some('=') => TokenType::Lte,
                     Some('<') => {
                         let _ = self.read_char();
                         match self.peek_char {
                             Some('=') => {
                                 let _ = self.read_char();
-                                Token::ShlEq
+                                TokenType::ShlEq
                             }
-                            _ => Token::Shl,
+                            _ => TokenType::Shl,
                         }
                     }
-                    _ => Token::Lt,
+                    _ => TokenType::Lt,
                 };
-                if token != Token::Lt {
+                if ty == TokenType::Lte {
                     let _ = self.read_char();
                 }
-                token
+                ty
             }
             Some(ch) => {
                 if ch.is_ascii_alphabetic() || ch == '_' {
@@ -213,17 +253,41 @@ impl<'a> Lexer<'a> {
                     return Ok(Token::from_ident(key));
                 } else if ch.is_numeric() {
                     let key = self.read_number()?;
-                    return Ok(Token::Num(key));
+                    return Ok(Token::new(TokenType::Num, key));
                 }
+                // Skip the character so that the caller can keep lexing.
+                let _ = self.read_char();
                 return Err(format!("Unexpected character {}", ch));
             }
-            None => Token::Eof,
+            None => return Ok(Token::new(TokenType::Eof, "")),
         };
         let _ = self.read_char();
-        Ok(token)
+        Ok(Token::new(ty, &self.data[start_pos..self.cur_pos]))
     }
 }
 
+/// Interprets the escape sequences of a `pure` string literal.
+pub fn unescape(data: &str) -> String {
+    let mut unescaped = String::with_capacity(data.len());
+    let mut chars = data.chars();
+    while let Some(ch) = chars.next() {
+        if ch != '\\' {
+            unescaped.push(ch);
+            continue;
+        }
+        match chars.next() {
+            Some('n') => unescaped.push('\n'),
+            Some('t') => unescaped.push('\t'),
+            Some('v') => unescaped.push('\x0B'),
+            Some('r') => unescaped.push('\r'),
+            Some('b') => unescaped.push('\x08'),
+            Some(ch) => unescaped.push(ch),
+            None => unescaped.push('\\'),
+        }
+    }
+    unescaped
+}
+
 impl<'a> Lexer<'a> {
     /// Read `pure` and `impure` strings
     ///
@@ -235,7 +299,9 @@ impl<'a> Lexer<'a> {
         let cur_pos = self.cur_pos;
         while let Some(ch) = self.cur_char {
             if ch == '"' {
-                return Ok(&self.data[cur_pos..self.cur_pos]);
+                let literal = &self.data[cur_pos..self.cur_pos];
+                let _ = self.read_char();
+                return Ok(literal);
             }
             let _ = self.read_char();
         }
@@ -246,6 +312,9 @@ impl<'a> Lexer<'a> {
         let _ = self.read_char();
         let cur_pos = self.cur_pos;
         let mut on_escape = false;
+        // The string is read until the end even if it contains an invalid
+        // escape sequence, so that lexing can continue after the error.
+        let mut error = None;
         while let Some(ch) = self.cur_char {
             if ch == '\\' && !on_escape {
                 on_escape = true;
@@ -253,17 +322,24 @@ impl<'a> Lexer<'a> {
                 match ch {
                     'n' | 't' | 'v' | 'r' | '\'' | '"' | 'b' | '\\' => {}
                     _ => {
-                        return Err(format!(
-                            "Unexpected escape character. \
-                                             Expected one of '\\n, \\t, \\v, \\r, \\', \\\", \
-                                             \\b', got \\{}",
-                            self.cur_char.unwrap()
-                        ))
+                        error = error.or_else(|| {
+                            Some(format!(
+                                "Unexpected escape character. \
+                                 Expected one of '\\n, \\t, \\v, \\r, \\', \\\", \
+                                 \\b', got \\{}",
+                                ch
+                            ))
+                        });
                     }
                 }
                 on_escape = false;
             } else if ch == '\'' {
-                return Ok(&self.data[cur_pos..self.cur_pos]);
+                let literal = &self.data[cur_pos..self.cur_pos];
+                let _ = self.read_char();
+                return match error {
+                    Some(error) => Err(error),
+                    None => Ok(literal),
+                };
             }
 
             let _ = self.read_char();
@@ -272,17 +348,14 @@ impl<'a> Lexer<'a> {
     }
 
     fn eat_whitespace_or_comment(&mut self) {
-        let mut in_comment = false;
         while let Some(ch) = self.cur_char {
-            if in_comment {
-                if ch == '\n' {
-                    in_comment = false;
-                }
-                let _ = self.read_char();
-                continue;
-            }
             if ch == '#' {
-                in_comment = true;
+                let (start_pos, line, col) = (self.cur_pos, self.line, self.col);
+                while !matches!(self.cur_char, None | Some('\n')) {
+                    let _ = self.read_char();
+                }
+                self.comments
+                    .push(Span::new(start_pos, self.cur_pos, line, col));
                 continue;
             }
             if ch != '\t' && ch != '\r' && ch != '\x0C' && ch != ' ' && ch != '\n' {
@@ -293,9 +366,17 @@ impl<'a> Lexer<'a> {
     }
 
     fn read_char(&mut self) -> Option<char> {
+        if let Some(ch) = self.cur_char {
+            self.cur_pos += ch.len_utf8();
+            if ch == '\n' {
+                self.line += 1;
+                self.col = 1;
+            } else {
+                self.col += 1;
+            }
+        }
         self.cur_char = self.peek_char;
         self.peek_char = self.peek_cursor.next();
-        self.cur_pos += 1;
         self.cur_char
     }
 
@@ -310,11 +391,10 @@ impl<'a> Lexer<'a> {
         &self.data[cur_pos..self.cur_pos]
     }
 
-    // TODO: No need to inc
-    fn read_hexadecimal(&mut self, start_pos: usize) -> LResult<i32> {
+    fn read_hexadecimal(&mut self, start_pos: usize) -> LResult<()> {
         while let Some(ch) = self.cur_char {
             if !ch.is_ascii_hexdigit() {
-                if ch != ' ' {
+                if ch.is_ascii_alphanumeric() || ch == '_' {
                     return Err(format!("Unexpected token in hexadecimal number {}", ch));
                 }
                 break;
@@ -323,63 +403,49 @@ impl<'a> Lexer<'a> {
         }
 
         if self.cur_pos - start_pos <= 2 {
-            return Err(format!(
-                "Some number needs to be present after 'x' in a hexadecimal number"
-            ));
+            return Err(
+                "Some number needs to be present after 'x' in a hexadecimal number".to_string(),
+            );
         }
-
-        i32::from_str_radix(&self.data[start_pos + 2..self.cur_pos], 16)
-            .map_err(|e| format!("Error occured while parsing hexadecimal number {}", e))
+        Ok(())
     }
 
-    fn read_octal(&mut self, start_pos: usize) -> LResult<i32> {
+    fn read_octal(&mut self) -> LResult<()> {
         while let Some(ch) = self.cur_char {
             if !ch.is_numeric() {
-                if ch != ' ' {
+                if ch.is_ascii_alphabetic() || ch == '_' {
                     return Err(format!("Unexpected token in octal number {}", ch));
                 }
                 break;
             }
             let _ = self.read_char();
         }
-        i32::from_str_radix(&self.data[start_pos..self.cur_pos], 8)
-            .map_err(|e| format!("Error occured while parsing octal number {}", e))
+        Ok(())
     }
 
-    fn read_number(&mut self) -> LResult<i32> {
+    /// Reads a number and returns its literal. The literal is validated here
+    /// so that [`Token::num_value`] never fails on a lexed token.
+    fn read_number(&mut self) -> LResult<&'a str> {
         let cur_pos = self.cur_pos;
 
-        if self.cur_char == Some('0') {
-            match self.peek_char {
-                Some('x') => {
-                    let _ = self.read_char();
-                    let _ = self.read_char();
-                    return self.read_hexadecimal(cur_pos);
-                }
-                Some(ch) => {
-                    if ch.is_numeric() {
-                        return self.read_octal(cur_pos);
-                    }
+        if self.cur_char == Some('0') && self.peek_char == Some('x') {
+            let _ = self.read_char();
+            let _ = self.read_char();
+            self.read_hexadecimal(cur_pos)?;
+        } else if self.cur_char == Some('0') {
+            self.read_octal()?;
+        } else {
+            while let Some(ch) = self.cur_char {
+                if !ch.is_numeric() {
+                    break;
                 }
-                ch => return Err(format!("Unexpected token while parsing a number: {:?}", ch)),
-            };
-        }
-
-        while let Some(ch) = self.cur_char {
-            if !ch.is_numeric() {
-                break;
+                let _ = self.read_char();
             }
-            let _ = self.read_char();
         }
 
-        self.data[cur_pos..self.cur_pos]
-            .parse::<i32>()
-            .map_err(|_| {
-                format!(
-                    "{} is not a valid number.",
-                    &self.data[cur_pos..self.cur_pos]
-                )
-            })
+        let literal = &self.data[cur_pos..self.cur_pos];
+        Token::new(TokenType::Num, literal).num_value()?;
+        Ok(literal)
     }
 }
 
@@ -389,34 +455,67 @@ mod tests {
 
     #[test]
     fn numbers() {
-        let data = "0xFF 016 32 018 0xCZ";
+        let data = "0xFF 016 32 0 018 0xCZ";
         let mut lexer = Lexer::new(data);
-        assert_eq!(Token::Num(0xff), lexer.next_token().unwrap());
-        assert_eq!(Token::Num(0o16), lexer.next_token().unwrap());
-        assert_eq!(Token::Num(32), lexer.next_token().unwrap());
+        for exp in &["0xFF", "016", "32", "0"] {
+            let tok = lexer.next_token().unwrap();
+            assert_eq!((TokenType::Num, *exp), (tok.ty, tok.literal));
+        }
         assert!(lexer.next_token().is_err());
         assert!(lexer.next_token().is_err());
     }
 
+    #[test]
+    fn number_values() {
+        let data = "0xFF 016 32 0 0xFFFFFFFF 2147483648";
+        let mut lexer = Lexer::new(data);
+        for exp in &[0xff, 0o16, 32, 0, 0xffff_ffff, 2_147_483_648] {
+            assert_eq!(Ok(*exp), lexer.next_token().unwrap().num_value());
+        }
+    }
+
     #[test]
     fn operators() {
         let ops = [
             "+", "-", "*", "/", "%", "=", "==", "!=", "&&", "||", ">", "<", ">=", "<=", "!", "&",
-            "|", "^", "+=", "-=", "/=", "%=", "*=", "^=", ">>", "<<", ">>=", "<<=", ">>>", "++",
-            "--", "**", "><", ">!<", "=~", "!~",
+            "|", "^", "~", "+=", "-=", "/=", "%=", "*=", "^=", ">>", "<<", ">>=", "<<=", ">>>",
+            ">>>=", "++", "--", "**", "><", ">!<", "=~", "!~",
         ];
         let data = ops.join(" ");
         let mut lexer = Lexer::new(data.as_str());
         let mut ops_iter = ops.iter();
         while let Ok(tok) = lexer.next_token() {
-            if tok == Token::Eof {
+            if tok.ty == TokenType::Eof {
                 break;
             }
-            assert_eq!(Token::from_str(ops_iter.next().unwrap()), tok);
+            let op = Token::from_op(ops_iter.next().unwrap());
+            assert_eq!((op.ty, op.literal), (tok.ty, tok.literal));
         }
         assert_eq!(ops_iter.next(), None);
     }
 
+    #[test]
+    fn adjacent_operators() {
+        let data = "a<<=b>!<c>>>d;@f";
+        let expected = [
+            TokenType::Ident,
+            TokenType::ShlEq,
+            TokenType::Ident,
+            TokenType::NSUBSTR,
+            TokenType::Ident,
+            TokenType::Ushr,
+            TokenType::Ident,
+            TokenType::SemiColon,
+            TokenType::At,
+            TokenType::Ident,
+            TokenType::Eof,
+        ];
+        let mut lexer = Lexer::new(data);
+        for exp in &expected {
+            assert_eq!(*exp, lexer.next_token().unwrap().ty);
+        }
+    }
+
     #[test]
     fn escape_chars() {
         let data = r#"
@@ -427,11 +526,58 @@ mod tests {
 
         let mut lexer = Lexer::new(data);
         for exp in &expected {
-            assert_eq!(Ok(Token::PureStr(exp)), lexer.next_token());
+            let tok = lexer.next_token().unwrap();
+            assert_eq!((TokenType::PureStr, *exp), (tok.ty, tok.literal));
         }
         assert!(lexer.next_token().is_err());
     }
 
+    #[test]
+    fn spans() {
+        let data = "if (a)\n  b = 'ü' + \"x\";\n$";
+        let mut lexer = Lexer::new(data);
+        let expected = [
+            Span::new(0, 2, 1, 1),
+            Span::new(3, 4, 1, 4),
+            Span::new(4, 5, 1, 5),
+            Span::new(5, 6, 1, 6),
+            Span::new(9, 10, 2, 3),
+            Span::new(11, 12, 2, 5),
+            Span::new(13, 17, 2, 7),
+            Span::new(18, 19, 2, 11),
+            Span::new(20, 23, 2, 13),
+            Span::new(23, 24, 2, 16),
+        ];
+        for exp in &expected {
+            assert_eq!(*exp, lexer.next_token().unwrap().span);
+        }
+        let err = lexer.next_token().unwrap_err();
+        assert_eq!(Span::new(25, 26, 3, 1), err.span);
+        assert_eq!("Unexpected character $ at 3:1", err.to_string());
+        assert_eq!(TokenType::Eof, lexer.next_token().unwrap().ty);
+    }
+
+    #[test]
+    fn comments() {
+        let data = "# head\na = \"#\"; #tail\r\n#";
+        let mut lexer = Lexer::new(data);
+        while lexer.next_token().unwrap().ty != TokenType::Eof {}
+        let comments: Vec<&str> = lexer
+            .comments()
+            .iter()
+            .map(|span| &data[span.start..span.end])
+            .collect();
+        assert_eq!(comments, ["# head", "#tail\r", "#"]);
+        assert_eq!(lexer.comments()[1], Span::new(16, 22, 2, 10));
+    }
+
+    #[test]
+    fn unescape_pure_str() {
+        assert_eq!("a\nb\t'\"\\", unescape(r#"a\nb\t\'\"\\"#));
+        assert_eq!("\x0B\r\x08", unescape(r#"\v\r\b"#));
+        assert_eq!("no escapes", unescape("no escapes"));
+    }
+
     #[test]
     fn keywords() {
         let data = r#"
@@ -439,14 +585,29 @@ mod tests {
             include local_var global_var repeat until
         "#;
 
-        let expected = vec![Token::If, Token::Else, Token::Function, Token::Null, Token::True,
-        Token::False, Token::Return, Token::For, Token::While, Token::Break, Token::Continue,
-        Token::Foreach, Token::Include, Token::LocalVar, Token::GlobalVar, Token::Repeat,
-        Token::Until];
+        let expected = vec![
+            TokenType::If,
+            TokenType::Else,
+            TokenType::Function,
+            TokenType::Null,
+            TokenType::True,
+            TokenType::False,
+            TokenType::Return,
+            TokenType::For,
+            TokenType::While,
+            TokenType::Break,
+            TokenType::Continue,
+            TokenType::Foreach,
+            TokenType::Include,
+            TokenType::LocalVar,
+            TokenType::GlobalVar,
+            TokenType::Repeat,
+            TokenType::Until,
+        ];
 
         let mut lexer = Lexer::new(data);
         for exp in expected {
-            assert_eq!(Ok(exp), lexer.next_token());
+            assert_eq!(Ok(exp), lexer.next_token().map(|tok| tok.ty));
         }
     }
 }
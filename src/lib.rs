@@ -1,4 +1,19 @@
-mod ast;
+pub mod arena;
+pub mod ast;
+pub mod builtins;
+pub mod cst;
+pub mod error;
+pub mod feed;
+pub mod format;
+pub mod graph;
+pub mod include;
+pub mod incremental;
+pub mod intern;
 pub mod lexer;
-mod parser;
+pub mod parser;
+pub mod resolve;
+pub mod sexp;
+pub mod span;
 pub mod token;
+pub mod unparse;
+pub mod visit;
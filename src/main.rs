@@ -1,20 +1,40 @@
+use nasl_transpiler::feed::collect_sources;
+use nasl_transpiler::format::format;
+use nasl_transpiler::graph::IncludeGraph;
+use nasl_transpiler::include::IncludeResolver;
 use nasl_transpiler::lexer::Lexer;
-use nasl_transpiler::token::Token;
+use nasl_transpiler::token::TokenType;
 
 use std::fs;
 use std::io;
+use std::path::PathBuf;
+use std::process;
+
+const FMT_USAGE: &str = "usage: nasl-transpiler fmt [--check] <file or directory>...";
+const DEPS_USAGE: &str =
+    "usage: nasl-transpiler deps [--format dot|json] [-I <include directory>]... <directory>";
 
 fn main() {
+    let args: Vec<String> = std::env::args().skip(1).collect();
+    match args.first().map(String::as_str) {
+        Some("fmt") => process::exit(fmt(&args[1..])),
+        Some("deps") => process::exit(deps(&args[1..])),
+        _ => dump_tokens(),
+    }
+}
+
+fn dump_tokens() {
     let data = fs::read_to_string("asd.nasl").unwrap();
     let mut lexer = Lexer::new(data.as_str());
     loop {
-        let data = lexer.next_token();
-        if data.is_err() {
-            println!("{}", data.unwrap_err());
-            break;
-        }
-        let data = data.unwrap();
-        if data == Token::Eof {
+        let data = match lexer.next_token() {
+            Ok(data) => data,
+            Err(e) => {
+                println!("{}", e);
+                break;
+            }
+        };
+        if data.ty == TokenType::Eof {
             break;
         }
         println!("{:?}", data);
@@ -22,3 +42,172 @@ fn main() {
         let _ = io::stdin().read_line(&mut input);
     }
 }
+
+/// Formats the given files and the `.nasl` and `.inc` files in the given
+/// directories in place. With `--check` the files are only listed if they
+/// are not formatted. Returns the exit code.
+fn fmt(args: &[String]) -> i32 {
+    let mut check = false;
+    let mut paths = Vec::new();
+    for arg in args {
+        match arg.as_str() {
+            "--check" => check = true,
+            "-h" | "--help" => {
+                println!("{}", FMT_USAGE);
+                return 0;
+            }
+            _ if arg.starts_with('-') => {
+                eprintln!("unknown option `{}`\n{}", arg, FMT_USAGE);
+                return 2;
+            }
+            _ => paths.push(PathBuf::from(arg)),
+        }
+    }
+    if paths.is_empty() {
+        eprintln!("{}", FMT_USAGE);
+        return 2;
+    }
+
+    let mut files = Vec::new();
+    for path in &paths {
+        if let Err(e) = collect_sources(path, &mut files) {
+            eprintln!("{}: {}", path.display(), e);
+            return 1;
+        }
+    }
+
+    let mut failed = false;
+    for file in &files {
+        let bytes = match fs::read(file) {
+            Ok(bytes) => bytes,
+            Err(e) => {
+                eprintln!("{}: {}", file.display(), e);
+                failed = true;
+                continue;
+            }
+        };
+        // Feed files are not necessarily UTF-8, but the formatter could not
+        // write them back unchanged.
+        let code = match String::from_utf8(bytes) {
+            Ok(code) => code,
+            Err(e) => {
+                // Everything before the error is valid.
+                let valid = std::str::from_utf8(&e.as_bytes()[..e.utf8_error().valid_up_to()])
+                    .unwrap_or_default();
+                let line = valid.matches('\n').count() + 1;
+                let col = valid
+                    .rsplit('\n')
+                    .next()
+                    .unwrap_or_default()
+                    .chars()
+                    .count()
+                    + 1;
+                eprintln!(
+                    "{}: invalid UTF-8 at {}:{}, the file is not formatted",
+                    file.display(),
+                    line,
+                    col
+                );
+                failed = true;
+                continue;
+            }
+        };
+        let formatted = match format(&code) {
+            Ok(formatted) => formatted,
+            Err(errors) => {
+                for e in errors {
+                    eprintln!("{}: {}", file.display(), e);
+                }
+                failed = true;
+                continue;
+            }
+        };
+        if formatted == code {
+            continue;
+        }
+        if check {
+            println!("{}", file.display());
+            failed = true;
+        } else if let Err(e) = fs::write(file, formatted) {
+            eprintln!("{}: {}", file.display(), e);
+            failed = true;
+        }
+    }
+    if failed {
+        1
+    } else {
+        0
+    }
+}
+
+/// Prints the include graph of the files in a directory. Includes are looked
+/// up in the directory and then in the `-I` directories. Files that cannot
+/// be included and syntax errors are reported, but do not fail the command.
+/// Returns the exit code.
+fn deps(args: &[String]) -> i32 {
+    let mut json = false;
+    let mut search_paths = Vec::new();
+    let mut dir = None;
+    let mut args = args.iter();
+    while let Some(arg) = args.next() {
+        match arg.as_str() {
+            "--format" => match args.next().map(String::as_str) {
+                Some("dot") => json = false,
+                Some("json") => json = true,
+                _ => {
+                    eprintln!("expected `dot` or `json` after `--format`\n{}", DEPS_USAGE);
+                    return 2;
+                }
+            },
+            "-I" => match args.next() {
+                Some(path) => search_paths.push(PathBuf::from(path)),
+                None => {
+                    eprintln!("expected a directory after `-I`\n{}", DEPS_USAGE);
+                    return 2;
+                }
+            },
+            "-h" | "--help" => {
+                println!("{}", DEPS_USAGE);
+                return 0;
+            }
+            _ if arg.starts_with('-') => {
+                eprintln!("unknown option `{}`\n{}", arg, DEPS_USAGE);
+                return 2;
+            }
+            _ if dir.is_none() => dir = Some(PathBuf::from(arg)),
+            _ => {
+                eprintln!("{}", DEPS_USAGE);
+                return 2;
+            }
+        }
+    }
+    let dir = match dir {
+        Some(dir) => dir,
+        None => {
+            eprintln!("{}", DEPS_USAGE);
+            return 2;
+        }
+    };
+
+    let mut includes = IncludeResolver::new();
+    includes.add_search_path(&dir);
+    for path in search_paths {
+        includes.add_search_path(path);
+    }
+    let (graph, errors) = match IncludeGraph::build(&dir, &mut includes) {
+        Ok(built) => built,
+        Err(e) => {
+            eprintln!("{}: {}", dir.display(), e);
+            return 1;
+        }
+    };
+    for e in errors {
+        eprintln!("{}", e);
+    }
+    if json {
+        print!("{}", graph.to_json());
+    } else {
+        print!("{}", graph.to_dot());
+    }
+    0
+}
@@ -1,113 +1,2103 @@
-use super::lexer::Lexer;
 use super::ast::*;
+use super::cst::{self, Checkpoint, NodeKind, SyntaxTree};
+use super::error::Diagnostic;
+use super::lexer::Lexer;
+use super::span::Span;
 use super::token::*;
 use std::collections::HashMap;
 
-enum Precedence {
-    Postfix,        // () [] -> . var++ var--
-    Prefix,         // +var -var ! ~ ++var --var
-    Multiplicative, // * -
-    Additive,       // + -
-    Shift,          // >> <<
-    Relational,     // < <= > >= >< >!< =~ !~
-    Equality,       // == !=
-    BitwiseAnd,     //  &
-    BitwiseXor,     // ^
-    BitwiseOr,      // |
-    LogicalAnd,     // &&
-    LogicalOr,      // ||
+type PResult<T> = Result<T, Diagnostic>;
+
+#[derive(Debug, PartialEq, PartialOrd, Clone, Copy)]
+pub(crate) enum Precedence {
+    Lowest,
+    Comma,          // ,
     Assignment,     // = += -= *= %= >>= <<= ^=
-    Comma           // ,
+    LogicalOr,      // ||
+    LogicalAnd,     // &&
+    BitwiseOr,      // |
+    BitwiseXor,     // ^
+    BitwiseAnd,     //  &
+    Equality,       // == !=
+    Relational,     // < <= > >= >< >!< =~ !~
+    Shift,          // >> << >>>
+    Additive,       // + -
+    Multiplicative, // * / %
+    Prefix,         // +var -var ! ~ ++var --var
+    Pow,            // **
+    Postfix,        // () [] var++ var--
 }
 
-type InfixFn<'a> = fn(&mut Parser<'a>, lhs: Expression<'a>) -> Result<Statement, String>;
-type PrefixFn<'a> = fn(&mut Parser<'a>) -> Result<Statement, String>;
+impl Precedence {
+    /// Precedence of the token when it is used as an infix operator.
+    fn of(ty: &TokenType) -> Self {
+        match ty {
+            TokenType::Assign
+            | TokenType::PlusEq
+            | TokenType::MinusEq
+            | TokenType::MulEq
+            | TokenType::DivEq
+            | TokenType::ModEq
+            | TokenType::XorEq
+            | TokenType::ShlEq
+            | TokenType::ShrEq
+            | TokenType::UshrEq => Precedence::Assignment,
+            TokenType::Or => Precedence::LogicalOr,
+            TokenType::And => Precedence::LogicalAnd,
+            TokenType::BwOr => Precedence::BitwiseOr,
+            TokenType::BwXor => Precedence::BitwiseXor,
+            TokenType::BwAnd => Precedence::BitwiseAnd,
+            TokenType::Equ | TokenType::Nequ => Precedence::Equality,
+            TokenType::Gt
+            | TokenType::Lt
+            | TokenType::Gte
+            | TokenType::Lte
+            | TokenType::Substr
+            | TokenType::NSUBSTR
+            | TokenType::ReMatch
+            | TokenType::NreMatch => Precedence::Relational,
+            TokenType::Shl | TokenType::Shr | TokenType::Ushr => Precedence::Shift,
+            TokenType::Plus | TokenType::Minus => Precedence::Additive,
+            TokenType::Mul | TokenType::Div | TokenType::Mod => Precedence::Multiplicative,
+            TokenType::Pow => Precedence::Pow,
+            TokenType::Lparan | TokenType::Lbracket | TokenType::Incr | TokenType::Decr => {
+                Precedence::Postfix
+            }
+            _ => Precedence::Lowest,
+        }
+    }
+
+    /// Precedence of a binary operator.
+    pub(crate) fn of_binary(op: BinaryOp) -> Self {
+        match op {
+            BinaryOp::Or => Precedence::LogicalOr,
+            BinaryOp::And => Precedence::LogicalAnd,
+            BinaryOp::BitOr => Precedence::BitwiseOr,
+            BinaryOp::BitXor => Precedence::BitwiseXor,
+            BinaryOp::BitAnd => Precedence::BitwiseAnd,
+            BinaryOp::Eq | BinaryOp::NotEq => Precedence::Equality,
+            BinaryOp::Gt
+            | BinaryOp::Lt
+            | BinaryOp::GtEq
+            | BinaryOp::LtEq
+            | BinaryOp::Substr
+            | BinaryOp::NotSubstr
+            | BinaryOp::ReMatch
+            | BinaryOp::NotReMatch => Precedence::Relational,
+            BinaryOp::Shl | BinaryOp::Shr | BinaryOp::UShr => Precedence::Shift,
+            BinaryOp::Add | BinaryOp::Sub => Precedence::Additive,
+            BinaryOp::Mul | BinaryOp::Div | BinaryOp::Mod => Precedence::Multiplicative,
+            BinaryOp::Pow => Precedence::Pow,
+        }
+    }
+}
+
+// The parse functions only build the kind of the expression, its span is
+// attached by `parse_expression`.
+type InfixFn<'a> =
+    fn(&mut Parser<'a>, lhs: Expression<&'a str>) -> PResult<ExpressionKind<&'a str>>;
+type PrefixFn<'a> = fn(&mut Parser<'a>) -> PResult<ExpressionKind<&'a str>>;
 
 pub struct Parser<'a> {
+    source: &'a str,
     lexer: Lexer<'a>,
+    peek_token: Option<Token<'a>>,
+    // Span of the last consumed token, i.e. where the current node ends.
+    prev_span: Span,
+    errors: Vec<Diagnostic>,
+    // Whether `_FCT_ANON_ARGS` is used in the function that is being parsed.
+    uses_anon_args: bool,
+    // The syntax tree, if one is built.
+    cst: Option<cst::Builder<'a>>,
     infix_fns: HashMap<TokenType, InfixFn<'a>>,
     prefix_fns: HashMap<TokenType, PrefixFn<'a>>,
 }
 
 impl<'a> Parser<'a> {
     pub fn new(data: &'a str) -> Self {
-        let mut infix_fns = HashMap::new();
-        let mut prefix_fns = HashMap::new();
+        Parser::starting_at(data, 0, 1, 1)
+    }
+
+    /// Parser that starts at byte offset `pos` of `data`, which is at `line`
+    /// and `col`. The spans of the nodes are relative to the start of
+    /// `data`.
+    pub(crate) fn starting_at(data: &'a str, pos: usize, line: usize, col: usize) -> Self {
+        let mut infix_fns: HashMap<TokenType, InfixFn<'a>> = HashMap::new();
+        let mut prefix_fns: HashMap<TokenType, PrefixFn<'a>> = HashMap::new();
+
         prefix_fns.insert(TokenType::Ident, Parser::parse_identifier);
+        prefix_fns.insert(TokenType::Num, Parser::parse_number);
+        prefix_fns.insert(TokenType::ImpureStr, Parser::parse_literal);
+        prefix_fns.insert(TokenType::PureStr, Parser::parse_literal);
+        prefix_fns.insert(TokenType::True, Parser::parse_literal);
+        prefix_fns.insert(TokenType::False, Parser::parse_literal);
+        prefix_fns.insert(TokenType::Null, Parser::parse_literal);
+        prefix_fns.insert(TokenType::Lparan, Parser::parse_grouped);
+        prefix_fns.insert(TokenType::At, Parser::parse_function_ref);
+        for ty in &[
+            TokenType::Plus,
+            TokenType::Minus,
+            TokenType::Not,
+            TokenType::BwNot,
+            TokenType::Incr,
+            TokenType::Decr,
+        ] {
+            prefix_fns.insert(*ty, Parser::parse_prefix);
+        }
+
+        for ty in &[
+            TokenType::Plus,
+            TokenType::Minus,
+            TokenType::Mul,
+            TokenType::Div,
+            TokenType::Mod,
+            TokenType::Pow,
+            TokenType::Equ,
+            TokenType::Nequ,
+            TokenType::Gt,
+            TokenType::Lt,
+            TokenType::Gte,
+            TokenType::Lte,
+            TokenType::And,
+            TokenType::Or,
+            TokenType::BwAnd,
+            TokenType::BwOr,
+            TokenType::BwXor,
+            TokenType::Shl,
+            TokenType::Shr,
+            TokenType::Ushr,
+            TokenType::Substr,
+            TokenType::NSUBSTR,
+            TokenType::ReMatch,
+            TokenType::NreMatch,
+        ] {
+            infix_fns.insert(*ty, Parser::parse_infix);
+        }
+        for ty in &[
+            TokenType::Assign,
+            TokenType::PlusEq,
+            TokenType::MinusEq,
+            TokenType::MulEq,
+            TokenType::DivEq,
+            TokenType::ModEq,
+            TokenType::XorEq,
+            TokenType::ShlEq,
+            TokenType::ShrEq,
+            TokenType::UshrEq,
+        ] {
+            infix_fns.insert(*ty, Parser::parse_assign);
+        }
+        infix_fns.insert(TokenType::Incr, Parser::parse_postfix);
+        infix_fns.insert(TokenType::Decr, Parser::parse_postfix);
+        infix_fns.insert(TokenType::Lparan, Parser::parse_call);
+        infix_fns.insert(TokenType::Lbracket, Parser::parse_index);
+
         Self {
-            lexer: Lexer::new(data),
+            source: data,
+            lexer: Lexer::starting_at(data, pos, line, col),
+            peek_token: None,
+            prev_span: Span::default(),
+            errors: Vec::new(),
+            uses_anon_args: false,
+            cst: None,
             infix_fns,
             prefix_fns,
         }
     }
 
-    pub fn parse(&mut self) -> Result<Vec<Statement>, String> {
+    /// Parses the input and fails if it contains any syntax error.
+    pub fn parse(&mut self) -> Result<Vec<Statement<&'a str>>, Vec<Diagnostic>> {
+        let (stmts, errors) = self.parse_recovering();
+        if errors.is_empty() {
+            Ok(stmts)
+        } else {
+            Err(errors)
+        }
+    }
+
+    /// Parses the input into a lossless syntax tree, see the `cst` module.
+    /// Recovers from errors like `parse_recovering`, the tree has the input
+    /// that does not parse in `Error` nodes.
+    pub fn parse_cst(&mut self) -> (SyntaxTree<'a>, Vec<Diagnostic>) {
+        self.cst = Some(cst::Builder::new(self.source));
+        let (_, errors) = self.parse_recovering();
+        let builder = self.cst.take().expect("syntax tree builder");
+        (builder.finish(self.lexer.comments()), errors)
+    }
+
+    /// Parses the whole input without stopping at the first syntax error.
+    ///
+    /// When a statement fails to parse, the error is recorded and the parser
+    /// skips to the end of the statement (`;`) or the enclosing block (`}`)
+    /// and continues from there. The statements that are parsed successfully
+    /// are returned together with every error that is found.
+    pub fn parse_recovering(&mut self) -> (Vec<Statement<&'a str>>, Vec<Diagnostic>) {
+        let stmts = self.parse_statements(TokenType::Eof);
+        (stmts, std::mem::take(&mut self.errors))
+    }
+
+    /// Parses top level statements like `parse_recovering`, but stops before
+    /// a statement that starts at a byte offset for which `stop` returns
+    /// true. That offset is returned too, `None` if the parser got to eof.
+    pub(crate) fn parse_recovering_until<F: FnMut(usize) -> bool>(
+        &mut self,
+        mut stop: F,
+    ) -> (Vec<Statement<&'a str>>, Vec<Diagnostic>, Option<usize>) {
+        let mut stopped = None;
+        let stmts = self.parse_statements_while(false, |token| {
+            if stop(token.span.start) {
+                stopped = Some(token.span.start);
+            }
+            stopped.is_none()
+        });
+        (stmts, std::mem::take(&mut self.errors), stopped)
+    }
+
+    /// Spans of the comments of the input, once it has been parsed.
+    pub fn comments(&self) -> &[Span] {
+        self.lexer.comments()
+    }
+
+    /// Parses statements until `end` or eof, recovering from errors.
+    fn parse_statements(&mut self, end: TokenType) -> Vec<Statement<&'a str>> {
+        self.parse_statements_while(end == TokenType::Rbrace, |token| token.ty != end)
+    }
+
+    /// Parses statements as long as `more` accepts their first token or
+    /// until eof, recovering from errors. `in_block` tells whether the
+    /// statements are inside of a block.
+    fn parse_statements_while<F: FnMut(&Token<'a>) -> bool>(
+        &mut self,
+        in_block: bool,
+        mut more: F,
+    ) -> Vec<Statement<&'a str>> {
         let mut stmts = Vec::new();
-        while let Some(stmt) = self.parse_statement() {
-            stmts.push(stmt?);
+        loop {
+            match self.peek() {
+                Ok(token) if token.ty == TokenType::Eof || !more(&token) => break,
+                Ok(token) => {
+                    let checkpoint = self.checkpoint(token.span.start);
+                    match self.parse_statement() {
+                        Ok(stmt) => stmts.push(stmt),
+                        Err(e) => {
+                            self.errors.push(e);
+                            self.synchronize(in_block);
+                            self.finish_node(checkpoint, NodeKind::Error);
+                        }
+                    }
+                }
+                // The lexer skips the erroneous input, so we can go on.
+                Err(e) => self.errors.push(e),
+            }
+        }
+        stmts
+    }
+
+    /// Skips tokens until the end of the current statement.
+    ///
+    /// A `;` or a `}` ends the statement, unless they are inside a block
+    /// which is skipped as a whole. If `in_block` is true, the `}` of the
+    /// enclosing block is not consumed so that the block can be closed.
+    fn synchronize(&mut self, in_block: bool) {
+        let mut depth = 0;
+        loop {
+            let token = match self.peek() {
+                Ok(token) => token,
+                Err(e) => {
+                    self.errors.push(e);
+                    continue;
+                }
+            };
+            match token.ty {
+                TokenType::Eof => return,
+                TokenType::Rbrace if depth == 0 => {
+                    if !in_block {
+                        let _ = self.next_token();
+                    }
+                    return;
+                }
+                TokenType::SemiColon if depth == 0 => {
+                    let _ = self.next_token();
+                    return;
+                }
+                TokenType::Lbrace => {
+                    let _ = self.next_token();
+                    depth += 1;
+                }
+                TokenType::Rbrace => {
+                    let _ = self.next_token();
+                    depth -= 1;
+                    if depth == 0 {
+                        return;
+                    }
+                }
+                _ => {
+                    let _ = self.next_token();
+                }
+            }
+        }
+    }
+
+    fn parse_statement(&mut self) -> PResult<Statement<&'a str>> {
+        let checkpoint = self.start_node()?;
+        let start = self.peek()?.span;
+        let kind = match self.peek()?.ty {
+            TokenType::Lbrace => self.parse_block()?,
+            TokenType::If => self.parse_if()?,
+            TokenType::While => self.parse_while()?,
+            TokenType::For => self.parse_for()?,
+            TokenType::Foreach => self.parse_foreach()?,
+            TokenType::Repeat => self.parse_repeat()?,
+            TokenType::Function => self.parse_function_def()?,
+            TokenType::Return => self.parse_return()?,
+            TokenType::Include => self.parse_include()?,
+            TokenType::LocalVar | TokenType::GlobalVar => self.parse_declaration()?,
+            TokenType::Break => {
+                self.next_token()?;
+                self.expect_token(TokenType::SemiColon, "after `break`")?;
+                StatementKind::Break
+            }
+            TokenType::Continue => {
+                self.next_token()?;
+                self.expect_token(TokenType::SemiColon, "after `continue`")?;
+                StatementKind::Continue
+            }
+            TokenType::SemiColon => {
+                self.next_token()?;
+                StatementKind::Empty
+            }
+            _ => self.parse_expression_statement()?,
+        };
+        self.finish_node(checkpoint, NodeKind::of_statement(&kind));
+        Ok(Statement::new(kind, self.span_from(start)))
+    }
+
+    // $expr; or $call x $count;
+    fn parse_expression_statement(&mut self) -> PResult<StatementKind<&'a str>> {
+        let expr = self.parse_expression(Precedence::Lowest)?;
+
+        // An identifier can not follow a complete expression, so an `x`
+        // here is always the repetition operator and never a variable.
+        let token = self.peek()?;
+        if token.ty != TokenType::Ident || token.literal != "x" {
+            self.expect_token(TokenType::SemiColon, "after expression")?;
+            return Ok(StatementKind::Expression(Box::new(expr)));
+        }
+        self.next_token()?;
+        if !matches!(expr.kind, ExpressionKind::FunctionCall { .. }) {
+            return Err(Diagnostic::syntax(
+                "only function calls can be repeated with `x`",
+                token.span,
+            ));
+        }
+        let count = self.parse_expression(Precedence::Lowest)?;
+        self.expect_token(TokenType::SemiColon, "after repeat count")?;
+        Ok(StatementKind::Repeated {
+            call: Box::new(expr),
+            count: Box::new(count),
+        })
+    }
+
+    // { $statements }
+    fn parse_block(&mut self) -> PResult<StatementKind<&'a str>> {
+        self.expect_token(TokenType::Lbrace, "to open a block")?;
+        let statements = self.parse_statements(TokenType::Rbrace);
+        self.expect_token(TokenType::Rbrace, "to close the block")?;
+        Ok(StatementKind::Block { statements })
+    }
+
+    fn parse_if(&mut self) -> PResult<StatementKind<&'a str>> {
+        self.expect_token(TokenType::If, "")?;
+        let cond = self.parse_condition("if")?;
+        let if_block = self.parse_statement()?;
+        let else_block = if self.peek_is(TokenType::Else)? {
+            self.next_token()?;
+            Some(Box::new(self.parse_statement()?))
+        } else {
+            None
+        };
+        Ok(StatementKind::If {
+            cond: Box::new(cond),
+            if_block: Box::new(if_block),
+            else_block,
+        })
+    }
+
+    fn parse_while(&mut self) -> PResult<StatementKind<&'a str>> {
+        self.expect_token(TokenType::While, "")?;
+        let cond = self.parse_condition("while")?;
+        let block = self.parse_statement()?;
+        Ok(StatementKind::While {
+            cond: Box::new(cond),
+            block: Box::new(block),
+        })
+    }
+
+    fn parse_for(&mut self) -> PResult<StatementKind<&'a str>> {
+        self.expect_token(TokenType::For, "")?;
+        self.expect_token(TokenType::Lparan, "after `for`")?;
+        let init_expr = self.parse_optional_sequence(TokenType::SemiColon)?;
+        self.expect_token(TokenType::SemiColon, "after for-initializer")?;
+        let cond = self.parse_optional_expression(TokenType::SemiColon)?;
+        self.expect_token(TokenType::SemiColon, "after for-condition")?;
+        let end_expr = self.parse_optional_sequence(TokenType::Rparan)?;
+        self.expect_token(TokenType::Rparan, "after for-step")?;
+        let block = self.parse_statement()?;
+        Ok(StatementKind::For {
+            init_expr,
+            cond,
+            end_expr,
+            block: Box::new(block),
+        })
+    }
+
+    fn parse_foreach(&mut self) -> PResult<StatementKind<&'a str>> {
+        self.expect_token(TokenType::Foreach, "")?;
+        let var = self.expect_token(TokenType::Ident, "after `foreach`")?;
+        self.expect_token(TokenType::Lparan, "after foreach variable")?;
+        let array = self.parse_expression(Precedence::Lowest)?;
+        self.expect_token(TokenType::Rparan, "after foreach array")?;
+        let block = self.parse_statement()?;
+        Ok(StatementKind::Foreach {
+            var: Ident {
+                name: var.literal,
+                span: var.span,
+            },
+            array: Box::new(array),
+            block: Box::new(block),
+        })
+    }
+
+    fn parse_repeat(&mut self) -> PResult<StatementKind<&'a str>> {
+        self.expect_token(TokenType::Repeat, "")?;
+        let block = self.parse_statement()?;
+        self.expect_token(TokenType::Until, "after repeat-block")?;
+        let cond = self.parse_expression(Precedence::Lowest)?;
+        self.expect_token(TokenType::SemiColon, "after until-condition")?;
+        Ok(StatementKind::Repeat {
+            block: Box::new(block),
+            cond: Box::new(cond),
+        })
+    }
+
+    fn parse_function_def(&mut self) -> PResult<StatementKind<&'a str>> {
+        self.expect_token(TokenType::Function, "")?;
+        let ident = self.expect_token(TokenType::Ident, "after `function`")?;
+        self.expect_token(TokenType::Lparan, "after function name")?;
+        let mut params: Vec<Ident<&'a str>> = Vec::new();
+        while !self.peek_is(TokenType::Rparan)? {
+            let param = self.expect_token(TokenType::Ident, "in parameter list")?;
+            // A duplicate does not make the rest of the function unusable,
+            // so it is reported without failing.
+            if params.iter().any(|p| p.name == param.literal) {
+                self.errors.push(Diagnostic::syntax(
+                    format!("duplicate parameter `{}`", param.literal),
+                    param.span,
+                ));
+            } else {
+                params.push(Ident {
+                    name: param.literal,
+                    span: param.span,
+                });
+            }
+            if !self.peek_is(TokenType::Comma)? {
+                break;
+            }
+            self.next_token()?;
+        }
+        self.expect_token(TokenType::Rparan, "after parameter list")?;
+
+        let checkpoint = self.start_node()?;
+        let start = self.peek()?.span;
+        let outer_uses_anon_args = std::mem::replace(&mut self.uses_anon_args, false);
+        let body = self.parse_block();
+        let uses_anon_args = std::mem::replace(&mut self.uses_anon_args, outer_uses_anon_args);
+        let body = body?;
+        self.finish_node(checkpoint, NodeKind::Block);
+        Ok(StatementKind::FunctionDef {
+            ident: Ident {
+                name: ident.literal,
+                span: ident.span,
+            },
+            params,
+            uses_anon_args,
+            body: Box::new(Statement::new(body, self.span_from(start))),
+        })
+    }
+
+    // local_var $ident [= $init], ...;
+    fn parse_declaration(&mut self) -> PResult<StatementKind<&'a str>> {
+        let scope = match self.next_token()?.ty {
+            TokenType::GlobalVar => VarScope::Global,
+            _ => VarScope::Local,
+        };
+        let mut vars = Vec::new();
+        loop {
+            let checkpoint = self.start_node()?;
+            let ident = self.expect_token(TokenType::Ident, "in variable declaration")?;
+            let init = if self.peek_is(TokenType::Assign)? {
+                self.next_token()?;
+                Some(Box::new(self.parse_expression(Precedence::Comma)?))
+            } else {
+                None
+            };
+            self.finish_node(checkpoint, NodeKind::VarDecl);
+            vars.push(VarDecl {
+                ident: Ident {
+                    name: ident.literal,
+                    span: ident.span,
+                },
+                init,
+            });
+            if !self.peek_is(TokenType::Comma)? {
+                break;
+            }
+            self.next_token()?;
+        }
+        self.expect_token(TokenType::SemiColon, "after variable declaration")?;
+        Ok(StatementKind::Declaration { scope, vars })
+    }
+
+    fn parse_include(&mut self) -> PResult<StatementKind<&'a str>> {
+        self.expect_token(TokenType::Include, "")?;
+        self.expect_token(TokenType::Lparan, "after `include`")?;
+        let path = self.peek()?;
+        if path.ty != TokenType::ImpureStr && path.ty != TokenType::PureStr {
+            return Err(unexpected_token(&path, "string", "as include path"));
+        }
+        self.next_token()?;
+        self.expect_token(TokenType::Rparan, "after include path")?;
+        self.expect_token(TokenType::SemiColon, "after include")?;
+        Ok(StatementKind::Include {
+            path: path.literal,
+            path_span: path.span,
+        })
+    }
+
+    fn parse_return(&mut self) -> PResult<StatementKind<&'a str>> {
+        self.expect_token(TokenType::Return, "")?;
+        let expr = self.parse_optional_expression(TokenType::SemiColon)?;
+        self.expect_token(TokenType::SemiColon, "after return value")?;
+        Ok(StatementKind::Return { expr })
+    }
+
+    // ($cond)
+    fn parse_condition(&mut self, keyword: &str) -> PResult<Expression<&'a str>> {
+        self.expect_token(TokenType::Lparan, &format!("after `{}`", keyword))?;
+        let cond = self.parse_expression(Precedence::Lowest)?;
+        self.expect_token(TokenType::Rparan, &format!("after {}-condition", keyword))?;
+        Ok(cond)
+    }
+
+    /// Parses an expression unless the next token is `end`.
+    fn parse_optional_expression(
+        &mut self,
+        end: TokenType,
+    ) -> PResult<Option<Box<Expression<&'a str>>>> {
+        if self.peek_is(end)? {
+            return Ok(None);
+        }
+        Ok(Some(Box::new(self.parse_expression(Precedence::Lowest)?)))
+    }
+
+    /// Parses a comma separated sequence of expressions unless the next token
+    /// is `end`. A sequence of a single expression is that expression.
+    fn parse_optional_sequence(
+        &mut self,
+        end: TokenType,
+    ) -> PResult<Option<Box<Expression<&'a str>>>> {
+        if self.peek_is(end)? {
+            return Ok(None);
+        }
+        let checkpoint = self.start_node()?;
+        let start = self.peek()?.span;
+        let mut exprs = vec![self.parse_expression(Precedence::Comma)?];
+        while self.peek_is(TokenType::Comma)? {
+            self.next_token()?;
+            exprs.push(self.parse_expression(Precedence::Comma)?);
+        }
+        let expr = if exprs.len() == 1 {
+            exprs.remove(0)
+        } else {
+            self.finish_node(checkpoint, NodeKind::Sequence);
+            Expression::new(ExpressionKind::Sequence(exprs), self.span_from(start))
+        };
+        Ok(Some(Box::new(expr)))
+    }
+
+    fn parse_expression(&mut self, precedence: Precedence) -> PResult<Expression<&'a str>> {
+        // Infix expressions take in the expressions before them, so they all
+        // start here.
+        let checkpoint = self.start_node()?;
+        let token = self.peek()?;
+        let prefix_fn = match self.prefix_fns.get(&token.ty) {
+            Some(prefix_fn) => *prefix_fn,
+            None => {
+                return Err(Diagnostic::syntax(
+                    format!("expected expression, found {}", token),
+                    token.span,
+                ))
+            }
+        };
+        let kind = prefix_fn(self)?;
+        self.finish_node(checkpoint, NodeKind::of_expression(&kind));
+        let mut lhs = Expression::new(kind, self.span_from(token.span));
+
+        loop {
+            let token = self.peek()?;
+            if precedence >= Precedence::of(&token.ty) {
+                break;
+            }
+            let infix_fn = match self.infix_fns.get(&token.ty) {
+                Some(infix_fn) => *infix_fn,
+                None => break,
+            };
+            let start = lhs.span;
+            let kind = infix_fn(self, lhs)?;
+            self.finish_node(checkpoint, NodeKind::of_expression(&kind));
+            lhs = Expression::new(kind, self.span_from(start));
+        }
+
+        Ok(lhs)
+    }
+
+    fn parse_identifier(&mut self) -> PResult<ExpressionKind<&'a str>> {
+        let token = self.expect_token(TokenType::Ident, "")?;
+        if token.literal == "_FCT_ANON_ARGS" {
+            self.uses_anon_args = true;
+        }
+        Ok(ExpressionKind::Identifier(token.literal))
+    }
+
+    fn parse_number(&mut self) -> PResult<ExpressionKind<&'a str>> {
+        let token = self.expect_token(TokenType::Num, "")?;
+        let value = token
+            .num_value()
+            .map_err(|e| Diagnostic::syntax(e, token.span))?;
+        Ok(ExpressionKind::Number(value))
+    }
+
+    fn parse_literal(&mut self) -> PResult<ExpressionKind<&'a str>> {
+        let token = self.next_token()?;
+        let kind = match token.ty {
+            TokenType::ImpureStr => ExpressionKind::String(token.literal),
+            TokenType::PureStr => ExpressionKind::Data(token.literal),
+            TokenType::True => ExpressionKind::Boolean(true),
+            TokenType::False => ExpressionKind::Boolean(false),
+            TokenType::Null => ExpressionKind::Null,
+            _ => return Err(unexpected_token(&token, "literal", "")),
+        };
+        Ok(kind)
+    }
+
+    // @$ident
+    fn parse_function_ref(&mut self) -> PResult<ExpressionKind<&'a str>> {
+        self.expect_token(TokenType::At, "")?;
+        let ident = self.expect_token(TokenType::Ident, "after `@`")?;
+        Ok(ExpressionKind::FunctionRef(Ident {
+            name: ident.literal,
+            span: ident.span,
+        }))
+    }
+
+    // ($expr)
+    fn parse_grouped(&mut self) -> PResult<ExpressionKind<&'a str>> {
+        self.expect_token(TokenType::Lparan, "")?;
+        let expr = self.parse_expression(Precedence::Lowest)?;
+        self.expect_token(TokenType::Rparan, "after parenthesized expression")?;
+        Ok(ExpressionKind::Grouped(Box::new(expr)))
+    }
+
+    fn parse_prefix(&mut self) -> PResult<ExpressionKind<&'a str>> {
+        let token = self.next_token()?;
+        let op = UnaryOp::from_token(&token.ty)
+            .ok_or_else(|| unexpected_token(&token, "prefix operator", ""))?;
+        let expr = self.parse_expression(Precedence::Prefix)?;
+        Ok(ExpressionKind::Prefix {
+            op,
+            expr: Box::new(expr),
+        })
+    }
+
+    fn parse_infix(&mut self, lhs: Expression<&'a str>) -> PResult<ExpressionKind<&'a str>> {
+        let token = self.next_token()?;
+        let op = BinaryOp::from_token(&token.ty)
+            .ok_or_else(|| unexpected_token(&token, "infix operator", ""))?;
+        // `**` is right associative, so its right hand side may contain
+        // another `**`.
+        let precedence = match op {
+            BinaryOp::Pow => Precedence::Prefix,
+            _ => Precedence::of(&token.ty),
+        };
+        let rhs = self.parse_expression(precedence)?;
+        Ok(ExpressionKind::Infix {
+            l_expr: Box::new(lhs),
+            op,
+            r_expr: Box::new(rhs),
+        })
+    }
+
+    fn parse_assign(&mut self, lhs: Expression<&'a str>) -> PResult<ExpressionKind<&'a str>> {
+        let token = self.next_token()?;
+        let op = AssignOp::from_token(&token.ty)
+            .ok_or_else(|| unexpected_token(&token, "assignment operator", ""))?;
+        match lhs.kind {
+            ExpressionKind::Identifier(_) | ExpressionKind::Index { .. } => {}
+            _ => {
+                return Err(Diagnostic::syntax(
+                    format!("invalid left hand side of `{}`", op),
+                    token.span,
+                ))
+            }
+        }
+        // Assignments are right associative.
+        let rhs = self.parse_expression(Precedence::Comma)?;
+        Ok(ExpressionKind::Assign {
+            target: Box::new(lhs),
+            op,
+            expr: Box::new(rhs),
+        })
+    }
+
+    fn parse_postfix(&mut self, lhs: Expression<&'a str>) -> PResult<ExpressionKind<&'a str>> {
+        let token = self.next_token()?;
+        let op = UnaryOp::from_token(&token.ty)
+            .ok_or_else(|| unexpected_token(&token, "postfix operator", ""))?;
+        Ok(ExpressionKind::Postfix {
+            expr: Box::new(lhs),
+            op,
+        })
+    }
+
+    // $ident($args)
+    fn parse_call(&mut self, lhs: Expression<&'a str>) -> PResult<ExpressionKind<&'a str>> {
+        let lparan = self.peek()?;
+        let ident = match lhs.kind {
+            ExpressionKind::Identifier(name) => Ident {
+                name,
+                span: lhs.span,
+            },
+            _ => {
+                return Err(Diagnostic::syntax(
+                    "only named functions can be called",
+                    lparan.span,
+                ))
+            }
+        };
+        // The function is a name, not an expression.
+        self.unwrap_node();
+        self.expect_token(TokenType::Lparan, "")?;
+        let mut args: Vec<Arg<&'a str>> = Vec::new();
+        while !self.peek_is(TokenType::Rparan)? {
+            let checkpoint = self.start_node()?;
+            let arg = self.parse_expression(Precedence::Comma)?;
+            match arg.kind {
+                ExpressionKind::Identifier(name) if self.peek_is(TokenType::Colon)? => {
+                    self.unwrap_node();
+                    let colon = self.next_token()?;
+                    let value = self.parse_expression(Precedence::Comma)?;
+                    self.finish_node(checkpoint, NodeKind::NamedArg);
+                    // Like a duplicate parameter, this is reported without
+                    // failing and the first value is kept.
+                    if args
+                        .iter()
+                        .any(|arg| arg.name().is_some_and(|n| n.name == name))
+                    {
+                        self.errors.push(Diagnostic::syntax(
+                            format!("argument `{}` is passed more than once", name),
+                            colon.span,
+                        ));
+                    } else {
+                        let ident = Ident {
+                            name,
+                            span: arg.span,
+                        };
+                        args.push(Arg::Named(ident, value));
+                    }
+                }
+                _ => args.push(Arg::Anon(arg)),
+            }
+            if !self.peek_is(TokenType::Comma)? {
+                break;
+            }
+            self.next_token()?;
+        }
+        self.expect_token(TokenType::Rparan, "after arguments")?;
+        Ok(ExpressionKind::FunctionCall { ident, args })
+    }
+
+    // $expr[$index]
+    fn parse_index(&mut self, lhs: Expression<&'a str>) -> PResult<ExpressionKind<&'a str>> {
+        self.expect_token(TokenType::Lbracket, "")?;
+        let index = self.parse_expression(Precedence::Lowest)?;
+        self.expect_token(TokenType::Rbracket, "after index")?;
+        Ok(ExpressionKind::Index {
+            expr: Box::new(lhs),
+            index: Box::new(index),
+        })
+    }
+
+    /// Consumes and returns the next token if it is of type `ty`.
+    ///
+    /// Otherwise the token is left in place, so that error recovery can look
+    /// at it, and an error like "expected `)` after if-condition, found `{`"
+    /// is returned. `context` tells where the token is expected, it can be
+    /// empty if the token is already known to be there.
+    fn expect_token(&mut self, ty: TokenType, context: &str) -> PResult<Token<'a>> {
+        let token = self.peek()?;
+        if token.ty != ty {
+            return Err(unexpected_token(&token, ty, context));
+        }
+        self.next_token()
+    }
+
+    fn peek_is(&mut self, ty: TokenType) -> PResult<bool> {
+        Ok(self.peek()?.ty == ty)
+    }
+
+    fn peek(&mut self) -> PResult<Token<'a>> {
+        if let Some(token) = self.peek_token {
+            return Ok(token);
         }
-        Ok(stmts)
+        let token = self.lex()?;
+        self.peek_token = Some(token);
+        Ok(token)
     }
 
-    fn parse_statement(&mut self) -> Option<Result<Statement, String>> {
-        match self.lexer.next_token() {
-            _ => None
+    fn next_token(&mut self) -> PResult<Token<'a>> {
+        let token = match self.peek_token.take() {
+            Some(token) => token,
+            None => self.lex()?,
+        };
+        self.prev_span = token.span;
+        if let Some(cst) = &mut self.cst {
+            cst.token(token.ty, token.span, self.lexer.comments());
         }
+        Ok(token)
     }
 
-    fn parse_expression(&mut self) -> Result<Expression, String> {
-        unimplemented!()
+    /// Next token of the lexer. The input that it skips on error goes into
+    /// the syntax tree.
+    fn lex(&mut self) -> PResult<Token<'a>> {
+        let result = self.lexer.next_token();
+        if let (Err(e), Some(cst)) = (&result, &mut self.cst) {
+            cst.error(e.span, self.lexer.comments());
+        }
+        result
     }
 
-    fn parse_identifier(&mut self) -> Result<Expression, String> {
-        unimplemented!()
+    /// Start of the syntax tree node of the next token.
+    fn start_node(&mut self) -> PResult<Checkpoint> {
+        let start = self.peek()?.span.start;
+        Ok(self.checkpoint(start))
     }
 
-    fn expect_token(&mut self, token: Token) -> Result<(), ()> {
-        Ok(())
+    /// Start of a syntax tree node at byte offset `start`, where the next
+    /// token is.
+    fn checkpoint(&mut self, start: usize) -> Checkpoint {
+        match &mut self.cst {
+            Some(cst) => {
+                cst.trivia(start, self.lexer.comments());
+                cst.checkpoint()
+            }
+            None => Checkpoint::default(),
+        }
     }
 
-    fn next_token(&mut self) -> Result<Token, String> {
-        self.lexer.next_token()
+    fn finish_node(&mut self, checkpoint: Checkpoint, kind: NodeKind) {
+        if let Some(cst) = &mut self.cst {
+            cst.finish_node(checkpoint, kind);
+        }
     }
 
+    fn unwrap_node(&mut self) {
+        if let Some(cst) = &mut self.cst {
+            cst.unwrap_node();
+        }
+    }
+
+    /// Span from `start` up to the last consumed token.
+    fn span_from(&self, start: Span) -> Span {
+        start.to(self.prev_span)
+    }
+}
+
+fn unexpected_token<T: std::fmt::Display>(found: &Token, expected: T, context: &str) -> Diagnostic {
+    let message = if context.is_empty() {
+        format!("expected {}, found {}", expected, found)
+    } else {
+        format!("expected {} {}, found {}", expected, context, found)
+    };
+    Diagnostic::syntax(message, found.span)
 }
 
 #[cfg(test)]
 mod tests {
     use super::*;
+    use crate::visit::{walk_expression_mut, walk_statement_mut, walk_statements_mut, VisitorMut};
 
-    #[test]
-    fn parser() {
-        let parser = Parser::new("a + b * c;");
-        let stmts = parser.parse().unwrap();
-        println!("{:?}", stmts);
+    // Nodes without location, to be compared with the output of
+    // `without_spans`.
+    fn e<S>(kind: ExpressionKind<S>) -> Expression<S> {
+        Expression::new(kind, Span::default())
+    }
+
+    fn be<S>(kind: ExpressionKind<S>) -> Box<Expression<S>> {
+        Box::new(e(kind))
+    }
+
+    fn s<S>(kind: StatementKind<S>) -> Statement<S> {
+        Statement::new(kind, Span::default())
+    }
+
+    fn bs<S>(kind: StatementKind<S>) -> Box<Statement<S>> {
+        Box::new(s(kind))
+    }
+
+    /// Resets the spans of all statements and expressions, so that tests
+    /// about the shape of the tree do not have to spell them out. The spans
+    /// of names (`Ident`) are kept.
+    fn without_spans(mut stmts: Vec<Statement<&str>>) -> Vec<Statement<&str>> {
+        struct ClearSpans;
+
+        impl<'a> VisitorMut<&'a str> for ClearSpans {
+            fn visit_statement_mut(&mut self, stmt: &mut Statement<&'a str>) {
+                stmt.span = Span::default();
+                walk_statement_mut(self, stmt);
+            }
+
+            fn visit_expression_mut(&mut self, expr: &mut Expression<&'a str>) {
+                expr.span = Span::default();
+                walk_expression_mut(self, expr);
+            }
+        }
+
+        walk_statements_mut(&mut ClearSpans, &mut stmts);
+        stmts
     }
 
     #[test]
     fn if_statement() {
-        let parser = Parser::new("if (a + 23 * b + c) {} else {}");
+        let mut parser = Parser::new("if (a + 23 * b + c) {} else {}");
         let stmts = parser.parse().unwrap();
-        assert_eq!(stmts, vec![
-            Statement::If {
-                cond: Box::new(
-                    Expression::Infix {
-                        l_expr: Box::new(Expression::Identifier("a")),
-                        op: "+",
-                        r_expr: Box::new(Expression::Infix {
-                            l_expr: Box::new(Expression::Number(23)),
-                            op: "*",
-                            r_expr: Box::new(Expression::Identifier("c")),
-                        })
-                    }
-                ),
-                if_block: Box::new(Statement::Block {
+        assert_eq!(
+            without_spans(stmts),
+            vec![s(StatementKind::If {
+                cond: be(ExpressionKind::Infix {
+                    l_expr: be(ExpressionKind::Infix {
+                        l_expr: be(ExpressionKind::Identifier("a")),
+                        op: BinaryOp::Add,
+                        r_expr: be(ExpressionKind::Infix {
+                            l_expr: be(ExpressionKind::Number(23)),
+                            op: BinaryOp::Mul,
+                            r_expr: be(ExpressionKind::Identifier("b")),
+                        }),
+                    }),
+                    op: BinaryOp::Add,
+                    r_expr: be(ExpressionKind::Identifier("c")),
+                }),
+                if_block: bs(StatementKind::Block {
                     statements: Vec::new(),
-                    }
-                ),
-                else_block: Some(Box::new(Statement::Block {
+                }),
+                else_block: Some(bs(StatementKind::Block {
                     statements: Vec::new(),
                 }))
-            }]);
+            })]
+        );
+    }
+
+    #[test]
+    fn operator_precedence() {
+        let mut parser = Parser::new("x = -a ** b ** c >< d || e;");
+        let stmts = parser.parse().unwrap();
+        let pow = e(ExpressionKind::Infix {
+            l_expr: be(ExpressionKind::Identifier("a")),
+            op: BinaryOp::Pow,
+            r_expr: be(ExpressionKind::Infix {
+                l_expr: be(ExpressionKind::Identifier("b")),
+                op: BinaryOp::Pow,
+                r_expr: be(ExpressionKind::Identifier("c")),
+            }),
+        });
+        let substr = e(ExpressionKind::Infix {
+            l_expr: be(ExpressionKind::Prefix {
+                op: UnaryOp::Minus,
+                expr: Box::new(pow),
+            }),
+            op: BinaryOp::Substr,
+            r_expr: be(ExpressionKind::Identifier("d")),
+        });
+        assert_eq!(
+            without_spans(stmts),
+            vec![s(StatementKind::Expression(be(ExpressionKind::Assign {
+                target: be(ExpressionKind::Identifier("x")),
+                op: AssignOp::Assign,
+                expr: be(ExpressionKind::Infix {
+                    l_expr: Box::new(substr),
+                    op: BinaryOp::Or,
+                    r_expr: be(ExpressionKind::Identifier("e")),
+                }),
+            })))]
+        );
+    }
+
+    #[test]
+    fn recovers_from_errors() {
+        let mut parser = Parser::new(
+            r#"
+            a = ;
+            function f(x) {
+                x = x + ;
+                return x;
+                if (x y) { a; b; }
+                $ c;
+            }
+            }
+            b = 1;
+            "#,
+        );
+        let (stmts, errors) = parser.parse_recovering();
+        assert_eq!(errors.len(), 5, "{:?}", errors);
+        assert_eq!(
+            without_spans(stmts),
+            vec![
+                s(StatementKind::FunctionDef {
+                    ident: Ident {
+                        name: "f",
+                        span: Span::new(40, 41, 3, 22),
+                    },
+                    params: vec![Ident {
+                        name: "x",
+                        span: Span::new(42, 43, 3, 24),
+                    }],
+                    uses_anon_args: false,
+                    body: bs(StatementKind::Block {
+                        statements: vec![
+                            s(StatementKind::Return {
+                                expr: Some(be(ExpressionKind::Identifier("x"))),
+                            }),
+                            s(StatementKind::Expression(be(ExpressionKind::Identifier(
+                                "c"
+                            )))),
+                        ],
+                    }),
+                }),
+                s(StatementKind::Expression(be(ExpressionKind::Assign {
+                    target: be(ExpressionKind::Identifier("b")),
+                    op: AssignOp::Assign,
+                    expr: be(ExpressionKind::Number(1)),
+                }))),
+            ]
+        );
+    }
+
+    #[test]
+    fn error_messages() {
+        let cases = [
+            (
+                "if (a\n\t&& b {}",
+                "expected `)` after if-condition, found `{` at 2:7",
+            ),
+            (
+                "while a) {}",
+                "expected `(` after `while`, found identifier `a` at 1:7",
+            ),
+            (
+                "for (i = 0 i < 5; i++) {}",
+                "expected `;` after for-initializer, found identifier `i` at 1:12",
+            ),
+            (
+                "function 1() {}",
+                "expected identifier after `function`, found number `1` at 1:10",
+            ),
+            (
+                "function f(a, 2) {}",
+                "expected identifier in parameter list, found number `2` at 1:15",
+            ),
+            (
+                "function f(a) return a;",
+                "expected `{` to open a block, found `return` at 1:15",
+            ),
+            (
+                "repeat a++; while (a);",
+                "expected `until` after repeat-block, found `while` at 1:13",
+            ),
+            ("f(a, b;", "expected `)` after arguments, found `;` at 1:7"),
+            ("a[1;", "expected `]` after index, found `;` at 1:4"),
+            (
+                "a = (1 + 2;",
+                "expected `)` after parenthesized expression, found `;` at 1:11",
+            ),
+            (
+                "return a b;",
+                "expected `;` after return value, found identifier `b` at 1:10",
+            ),
+            (
+                "a x 3;",
+                "only function calls can be repeated with `x` at 1:3",
+            ),
+            (
+                "f() x 3 y;",
+                "expected `;` after repeat count, found identifier `y` at 1:9",
+            ),
+            (
+                "f = @1;",
+                "expected identifier after `@`, found number `1` at 1:6",
+            ),
+            (
+                "include(http);",
+                "expected string as include path, found identifier `http` at 1:9",
+            ),
+            (
+                "include('a.inc')",
+                "expected `;` after include, found end of file at 1:17",
+            ),
+            (
+                "local_var;",
+                "expected identifier in variable declaration, found `;` at 1:10",
+            ),
+            (
+                "global_var a b;",
+                "expected `;` after variable declaration, found identifier `b` at 1:14",
+            ),
+            ("a = ;", "expected expression, found `;` at 1:5"),
+            (
+                "{ a; ",
+                "expected `}` to close the block, found end of file at 1:6",
+            ),
+            ("1 = a;", "invalid left hand side of `=` at 1:3"),
+            (
+                "f(a:1, a:2);",
+                "argument `a` is passed more than once at 1:9",
+            ),
+            (
+                "a = 99999999999999999999;",
+                "99999999999999999999 is not a valid number at 1:5",
+            ),
+            (
+                "a = 0x;",
+                "Some number needs to be present after 'x' in a hexadecimal number at 1:5",
+            ),
+        ];
+        for (code, message) in &cases {
+            let errors = Parser::new(code).parse().unwrap_err();
+            assert_eq!(errors[0].to_string(), *message, "{}", code);
+        }
+    }
+
+    #[test]
+    fn unclosed_block() {
+        let mut parser = Parser::new("function f() { a = 1;");
+        let (stmts, errors) = parser.parse_recovering();
+        assert!(stmts.is_empty());
+        assert_eq!(errors.len(), 1);
+        assert!(Parser::new("a = 1").parse().is_err());
+    }
+
+    #[test]
+    fn foreach_statement() {
+        let mut parser = Parser::new("foreach port (ports) x++; foreach k (keys(arr)) {}");
+        let stmts = parser.parse().unwrap();
+        assert_eq!(
+            without_spans(stmts),
+            vec![
+                s(StatementKind::Foreach {
+                    var: Ident {
+                        name: "port",
+                        span: Span::new(8, 12, 1, 9),
+                    },
+                    array: be(ExpressionKind::Identifier("ports")),
+                    block: bs(StatementKind::Expression(be(ExpressionKind::Postfix {
+                        expr: be(ExpressionKind::Identifier("x")),
+                        op: UnaryOp::Incr,
+                    }))),
+                }),
+                s(StatementKind::Foreach {
+                    var: Ident {
+                        name: "k",
+                        span: Span::new(34, 35, 1, 35),
+                    },
+                    array: be(ExpressionKind::FunctionCall {
+                        ident: Ident {
+                            name: "keys",
+                            span: Span::new(37, 41, 1, 38),
+                        },
+                        args: vec![Arg::Anon(e(ExpressionKind::Identifier("arr")))],
+                    }),
+                    block: bs(StatementKind::Block {
+                        statements: Vec::new()
+                    }),
+                }),
+            ]
+        );
+    }
+
+    #[test]
+    fn function_def() {
+        let mut parser = Parser::new(
+            "function f(a, b) { return a; }\nfunction g() { return _FCT_ANON_ARGS[0]; }",
+        );
+        let stmts = parser.parse().unwrap();
+        assert_eq!(
+            without_spans(stmts),
+            vec![
+                s(StatementKind::FunctionDef {
+                    ident: Ident {
+                        name: "f",
+                        span: Span::new(9, 10, 1, 10),
+                    },
+                    params: vec![
+                        Ident {
+                            name: "a",
+                            span: Span::new(11, 12, 1, 12),
+                        },
+                        Ident {
+                            name: "b",
+                            span: Span::new(14, 15, 1, 15),
+                        },
+                    ],
+                    uses_anon_args: false,
+                    body: bs(StatementKind::Block {
+                        statements: vec![s(StatementKind::Return {
+                            expr: Some(be(ExpressionKind::Identifier("a"))),
+                        })],
+                    }),
+                }),
+                s(StatementKind::FunctionDef {
+                    ident: Ident {
+                        name: "g",
+                        span: Span::new(40, 41, 2, 10),
+                    },
+                    params: Vec::new(),
+                    uses_anon_args: true,
+                    body: bs(StatementKind::Block {
+                        statements: vec![s(StatementKind::Return {
+                            expr: Some(be(ExpressionKind::Index {
+                                expr: be(ExpressionKind::Identifier("_FCT_ANON_ARGS")),
+                                index: be(ExpressionKind::Number(0)),
+                            })),
+                        })],
+                    }),
+                }),
+            ]
+        );
+    }
+
+    #[test]
+    fn duplicate_arguments() {
+        let mut parser = Parser::new("f(a:1, b:2, a:3);");
+        let (stmts, errors) = parser.parse_recovering();
+        assert_eq!(
+            errors,
+            vec![Diagnostic::syntax(
+                "argument `a` is passed more than once",
+                Span::new(13, 14, 1, 14)
+            )]
+        );
+        assert_eq!(crate::unparse::to_nasl(&stmts), "f(a:1, b:2);\n");
+    }
+
+    #[test]
+    fn duplicate_parameters() {
+        let mut parser = Parser::new("function f(a, b, a) { return b; }");
+        let (stmts, errors) = parser.parse_recovering();
+        assert_eq!(
+            errors,
+            vec![Diagnostic::syntax(
+                "duplicate parameter `a`",
+                Span::new(17, 18, 1, 18)
+            )]
+        );
+        match &stmts[..] {
+            [Statement {
+                kind: StatementKind::FunctionDef { params, .. },
+                ..
+            }] => {
+                let names: Vec<_> = params.iter().map(|p| p.name).collect();
+                assert_eq!(names, vec!["a", "b"]);
+            }
+            _ => panic!("unexpected statements {:?}", stmts),
+        }
+    }
+
+    #[test]
+    fn repeated_call() {
+        let mut parser =
+            Parser::new("send(socket:soc, data:d) x 10;\nx = x * 2;\nf() x x + 1;\nf(x) x x;");
+        let stmts = parser.parse().unwrap();
+        let call = |name, start, line, arg: Option<Expression<&'static str>>| {
+            be(ExpressionKind::FunctionCall {
+                ident: Ident {
+                    name,
+                    span: Span::new(start, start + 1, line, 1),
+                },
+                args: arg.into_iter().map(Arg::Anon).collect(),
+            })
+        };
+        let args = vec![
+            Arg::Named(
+                Ident {
+                    name: "socket",
+                    span: Span::new(5, 11, 1, 6),
+                },
+                e(ExpressionKind::Identifier("soc")),
+            ),
+            Arg::Named(
+                Ident {
+                    name: "data",
+                    span: Span::new(17, 21, 1, 18),
+                },
+                e(ExpressionKind::Identifier("d")),
+            ),
+        ];
+        assert_eq!(
+            without_spans(stmts),
+            vec![
+                s(StatementKind::Repeated {
+                    call: be(ExpressionKind::FunctionCall {
+                        ident: Ident {
+                            name: "send",
+                            span: Span::new(0, 4, 1, 1),
+                        },
+                        args,
+                    }),
+                    count: be(ExpressionKind::Number(10)),
+                }),
+                s(StatementKind::Expression(be(ExpressionKind::Assign {
+                    target: be(ExpressionKind::Identifier("x")),
+                    op: AssignOp::Assign,
+                    expr: be(ExpressionKind::Infix {
+                        l_expr: be(ExpressionKind::Identifier("x")),
+                        op: BinaryOp::Mul,
+                        r_expr: be(ExpressionKind::Number(2)),
+                    }),
+                }))),
+                s(StatementKind::Repeated {
+                    call: call("f", 42, 3, None),
+                    count: be(ExpressionKind::Infix {
+                        l_expr: be(ExpressionKind::Identifier("x")),
+                        op: BinaryOp::Add,
+                        r_expr: be(ExpressionKind::Number(1)),
+                    }),
+                }),
+                s(StatementKind::Repeated {
+                    call: call("f", 55, 4, Some(e(ExpressionKind::Identifier("x")))),
+                    count: be(ExpressionKind::Identifier("x")),
+                }),
+            ]
+        );
+    }
+
+    #[test]
+    fn include_statement() {
+        let mut parser = Parser::new("include(\"http_func.inc\");\ninclude('misc.inc');");
+        let stmts = parser.parse().unwrap();
+        assert_eq!(
+            without_spans(stmts),
+            vec![
+                s(StatementKind::Include {
+                    path: "http_func.inc",
+                    path_span: Span::new(8, 23, 1, 9),
+                }),
+                s(StatementKind::Include {
+                    path: "misc.inc",
+                    path_span: Span::new(34, 44, 2, 9),
+                }),
+            ]
+        );
+    }
+
+    #[test]
+    fn declarations() {
+        let mut parser = Parser::new("local_var a, b = 3;\nglobal_var g;");
+        let stmts = parser.parse().unwrap();
+        assert_eq!(
+            without_spans(stmts),
+            vec![
+                s(StatementKind::Declaration {
+                    scope: VarScope::Local,
+                    vars: vec![
+                        VarDecl {
+                            ident: Ident {
+                                name: "a",
+                                span: Span::new(10, 11, 1, 11),
+                            },
+                            init: None,
+                        },
+                        VarDecl {
+                            ident: Ident {
+                                name: "b",
+                                span: Span::new(13, 14, 1, 14),
+                            },
+                            init: Some(be(ExpressionKind::Number(3))),
+                        },
+                    ],
+                }),
+                s(StatementKind::Declaration {
+                    scope: VarScope::Global,
+                    vars: vec![VarDecl {
+                        ident: Ident {
+                            name: "g",
+                            span: Span::new(31, 32, 2, 12),
+                        },
+                        init: None,
+                    }],
+                }),
+            ]
+        );
+    }
+
+    #[test]
+    fn for_sequences() {
+        let assign = |name, op, value| {
+            e(ExpressionKind::Assign {
+                target: be(ExpressionKind::Identifier(name)),
+                op,
+                expr: be(ExpressionKind::Number(value)),
+            })
+        };
+
+        let mut parser = Parser::new("for (i = 0, j = 0; i < n; i++, j += 2) {}");
+        let stmts = parser.parse().unwrap();
+        assert_eq!(
+            without_spans(stmts),
+            vec![s(StatementKind::For {
+                init_expr: Some(be(ExpressionKind::Sequence(vec![
+                    assign("i", AssignOp::Assign, 0),
+                    assign("j", AssignOp::Assign, 0),
+                ]))),
+                cond: Some(be(ExpressionKind::Infix {
+                    l_expr: be(ExpressionKind::Identifier("i")),
+                    op: BinaryOp::Lt,
+                    r_expr: be(ExpressionKind::Identifier("n")),
+                })),
+                end_expr: Some(be(ExpressionKind::Sequence(vec![
+                    e(ExpressionKind::Postfix {
+                        expr: be(ExpressionKind::Identifier("i")),
+                        op: UnaryOp::Incr,
+                    }),
+                    assign("j", AssignOp::Add, 2),
+                ]))),
+                block: bs(StatementKind::Block {
+                    statements: Vec::new(),
+                }),
+            })]
+        );
+
+        let mut parser = Parser::new("for (;;) {}");
+        assert_eq!(
+            without_spans(parser.parse().unwrap()),
+            vec![s(StatementKind::For {
+                init_expr: None,
+                cond: None,
+                end_expr: None,
+                block: bs(StatementKind::Block {
+                    statements: Vec::new(),
+                }),
+            })]
+        );
+    }
+
+    #[test]
+    fn grouped_expressions() {
+        let mut parser = Parser::new("x = (a + b) * c;");
+        let stmts = parser.parse().unwrap();
+        assert_eq!(
+            without_spans(stmts),
+            vec![s(StatementKind::Expression(be(ExpressionKind::Assign {
+                target: be(ExpressionKind::Identifier("x")),
+                op: AssignOp::Assign,
+                expr: be(ExpressionKind::Infix {
+                    l_expr: be(ExpressionKind::Grouped(be(ExpressionKind::Infix {
+                        l_expr: be(ExpressionKind::Identifier("a")),
+                        op: BinaryOp::Add,
+                        r_expr: be(ExpressionKind::Identifier("b")),
+                    }))),
+                    op: BinaryOp::Mul,
+                    r_expr: be(ExpressionKind::Identifier("c")),
+                }),
+            })))]
+        );
+        // Commas are not operators outside of `for` headers.
+        assert!(Parser::new("a = 1, b = 2;").parse().is_err());
+    }
+
+    #[test]
+    fn literals() {
+        let mut parser = Parser::new(r#"f("a\n", 'b\n', TRUE, FALSE, NULL);"#);
+        let stmts = parser.parse().unwrap();
+        assert_eq!(
+            without_spans(stmts),
+            vec![s(StatementKind::Expression(be(
+                ExpressionKind::FunctionCall {
+                    ident: Ident {
+                        name: "f",
+                        span: Span::new(0, 1, 1, 1),
+                    },
+                    args: vec![
+                        Arg::Anon(e(ExpressionKind::String("a\\n"))),
+                        Arg::Anon(e(ExpressionKind::Data("b\\n"))),
+                        Arg::Anon(e(ExpressionKind::Boolean(true))),
+                        Arg::Anon(e(ExpressionKind::Boolean(false))),
+                        Arg::Anon(e(ExpressionKind::Null)),
+                    ],
+                }
+            )))]
+        );
+    }
+
+    #[test]
+    fn calls_and_indexes() {
+        let mut parser = Parser::new("arr[i++] += f(1, port:p);");
+        let stmts = parser.parse().unwrap();
+        let port = Ident {
+            name: "port",
+            span: Span::new(17, 21, 1, 18),
+        };
+        assert_eq!(
+            without_spans(stmts),
+            vec![s(StatementKind::Expression(be(ExpressionKind::Assign {
+                target: be(ExpressionKind::Index {
+                    expr: be(ExpressionKind::Identifier("arr")),
+                    index: be(ExpressionKind::Postfix {
+                        expr: be(ExpressionKind::Identifier("i")),
+                        op: UnaryOp::Incr,
+                    }),
+                }),
+                op: AssignOp::Add,
+                expr: be(ExpressionKind::FunctionCall {
+                    ident: Ident {
+                        name: "f",
+                        span: Span::new(12, 13, 1, 13),
+                    },
+                    args: vec![
+                        Arg::Anon(e(ExpressionKind::Number(1))),
+                        Arg::Named(port, e(ExpressionKind::Identifier("p"))),
+                    ],
+                }),
+            })))]
+        );
+    }
+
+    #[test]
+    fn node_spans() {
+        let code = "if (a) {\n  x = -f(1)[0] + (2);\n} else return;\nfor (i = 0, j = 0;;) {}";
+        let stmts = Parser::new(code).parse().unwrap();
+        let text = |span: Span| &code[span.start..span.end];
+
+        assert_eq!(
+            text(stmts[0].span()),
+            "if (a) {\n  x = -f(1)[0] + (2);\n} else return;"
+        );
+        let (cond, if_block, else_block) = match &stmts[0].kind {
+            StatementKind::If {
+                cond,
+                if_block,
+                else_block: Some(else_block),
+            } => (cond, if_block, else_block),
+            _ => panic!("unexpected statement {:?}", stmts[0]),
+        };
+        assert_eq!(text(cond.span()), "a");
+        assert_eq!(text(if_block.span()), "{\n  x = -f(1)[0] + (2);\n}");
+        assert_eq!(text(else_block.span()), "return;");
+        assert_eq!(else_block.span().line, 3);
+        assert_eq!(else_block.span().col, 8);
+
+        let stmt = match &if_block.kind {
+            StatementKind::Block { statements } => &statements[0],
+            _ => panic!("unexpected statement {:?}", if_block),
+        };
+        assert_eq!(text(stmt.span()), "x = -f(1)[0] + (2);");
+        assert_eq!((stmt.span().line, stmt.span().col), (2, 3));
+        let expr = match &stmt.kind {
+            StatementKind::Expression(expr) => expr,
+            _ => panic!("unexpected statement {:?}", stmt),
+        };
+        assert_eq!(text(expr.span()), "x = -f(1)[0] + (2)");
+        let (l_expr, r_expr) = match &expr.kind {
+            ExpressionKind::Assign { expr, .. } => match &expr.kind {
+                ExpressionKind::Infix { l_expr, r_expr, .. } => (l_expr, r_expr),
+                _ => panic!("unexpected expression {:?}", expr),
+            },
+            _ => panic!("unexpected expression {:?}", expr),
+        };
+        assert_eq!(text(l_expr.span()), "-f(1)[0]");
+        assert_eq!(text(r_expr.span()), "(2)");
+        match &l_expr.kind {
+            ExpressionKind::Prefix { expr, .. } => assert_eq!(text(expr.span()), "f(1)[0]"),
+            _ => panic!("unexpected expression {:?}", l_expr),
+        }
+
+        assert_eq!(text(stmts[1].span()), "for (i = 0, j = 0;;) {}");
+        match &stmts[1].kind {
+            StatementKind::For {
+                init_expr: Some(init_expr),
+                ..
+            } => assert_eq!(text(init_expr.span()), "i = 0, j = 0"),
+            _ => panic!("unexpected statement {:?}", stmts[1]),
+        }
+    }
+
+    // Builders for the table of `constructs`.
+    type E = Expression<&'static str>;
+    type St = Statement<&'static str>;
+
+    fn id(name: &'static str) -> E {
+        e(ExpressionKind::Identifier(name))
+    }
+
+    fn num(value: i64) -> E {
+        e(ExpressionKind::Number(value))
+    }
+
+    fn prefix(op: UnaryOp, expr: E) -> E {
+        e(ExpressionKind::Prefix {
+            op,
+            expr: Box::new(expr),
+        })
+    }
+
+    fn postfix(expr: E, op: UnaryOp) -> E {
+        e(ExpressionKind::Postfix {
+            expr: Box::new(expr),
+            op,
+        })
+    }
+
+    fn infix(l_expr: E, op: BinaryOp, r_expr: E) -> E {
+        e(ExpressionKind::Infix {
+            l_expr: Box::new(l_expr),
+            op,
+            r_expr: Box::new(r_expr),
+        })
+    }
+
+    fn assign(target: E, op: AssignOp, expr: E) -> E {
+        e(ExpressionKind::Assign {
+            target: Box::new(target),
+            op,
+            expr: Box::new(expr),
+        })
+    }
+
+    fn index(expr: E, index: E) -> E {
+        e(ExpressionKind::Index {
+            expr: Box::new(expr),
+            index: Box::new(index),
+        })
+    }
+
+    fn grouped(expr: E) -> E {
+        e(ExpressionKind::Grouped(Box::new(expr)))
+    }
+
+    fn call(ident: &'static str, args: Vec<E>) -> E {
+        e(ExpressionKind::FunctionCall {
+            ident: Ident {
+                name: ident,
+                span: Span::default(),
+            },
+            args: args.into_iter().map(Arg::Anon).collect(),
+        })
+    }
+
+    fn expr(expr: E) -> St {
+        s(StatementKind::Expression(Box::new(expr)))
+    }
+
+    fn block(statements: Vec<St>) -> St {
+        s(StatementKind::Block { statements })
+    }
+
+    /// Replaces the spans of all names by `Span::default()`.
+    fn without_ident_spans(mut stmts: Vec<Statement<&str>>) -> Vec<Statement<&str>> {
+        struct ClearSpans;
+
+        impl<'a> VisitorMut<&'a str> for ClearSpans {
+            fn visit_ident_mut(&mut self, ident: &mut Ident<&'a str>) {
+                ident.span = Span::default();
+            }
+        }
+
+        walk_statements_mut(&mut ClearSpans, &mut stmts);
+        without_spans(stmts)
+    }
+
+    #[test]
+    fn constructs() {
+        let name = |name| Ident {
+            name,
+            span: Span::default(),
+        };
+        let cases: Vec<(&str, St)> = vec![
+            // Literals
+            ("a;", expr(id("a"))),
+            ("42;", expr(num(42))),
+            ("0x1f;", expr(num(31))),
+            ("017;", expr(num(15))),
+            ("\"s\\n\";", expr(e(ExpressionKind::String("s\\n")))),
+            ("'d';", expr(e(ExpressionKind::Data("d")))),
+            ("TRUE;", expr(e(ExpressionKind::Boolean(true)))),
+            ("FALSE;", expr(e(ExpressionKind::Boolean(false)))),
+            ("NULL;", expr(e(ExpressionKind::Null))),
+            ("@f;", expr(e(ExpressionKind::FunctionRef(name("f"))))),
+            // Prefix and postfix operators
+            ("-a;", expr(prefix(UnaryOp::Minus, id("a")))),
+            ("+a;", expr(prefix(UnaryOp::Plus, id("a")))),
+            ("!a;", expr(prefix(UnaryOp::Not, id("a")))),
+            ("~a;", expr(prefix(UnaryOp::BitNot, id("a")))),
+            ("++a;", expr(prefix(UnaryOp::Incr, id("a")))),
+            ("--a;", expr(prefix(UnaryOp::Decr, id("a")))),
+            ("a++;", expr(postfix(id("a"), UnaryOp::Incr))),
+            ("a--;", expr(postfix(id("a"), UnaryOp::Decr))),
+            (
+                "!a++;",
+                expr(prefix(UnaryOp::Not, postfix(id("a"), UnaryOp::Incr))),
+            ),
+            // Infix operators, from the loosest to the tightest binding
+            (
+                "a || b && c;",
+                expr(infix(
+                    id("a"),
+                    BinaryOp::Or,
+                    infix(id("b"), BinaryOp::And, id("c")),
+                )),
+            ),
+            (
+                "a && b | c;",
+                expr(infix(
+                    id("a"),
+                    BinaryOp::And,
+                    infix(id("b"), BinaryOp::BitOr, id("c")),
+                )),
+            ),
+            (
+                "a | b ^ c;",
+                expr(infix(
+                    id("a"),
+                    BinaryOp::BitOr,
+                    infix(id("b"), BinaryOp::BitXor, id("c")),
+                )),
+            ),
+            (
+                "a ^ b & c;",
+                expr(infix(
+                    id("a"),
+                    BinaryOp::BitXor,
+                    infix(id("b"), BinaryOp::BitAnd, id("c")),
+                )),
+            ),
+            (
+                "a & b == c;",
+                expr(infix(
+                    id("a"),
+                    BinaryOp::BitAnd,
+                    infix(id("b"), BinaryOp::Eq, id("c")),
+                )),
+            ),
+            (
+                "a != b < c;",
+                expr(infix(
+                    id("a"),
+                    BinaryOp::NotEq,
+                    infix(id("b"), BinaryOp::Lt, id("c")),
+                )),
+            ),
+            (
+                "a >= b << c;",
+                expr(infix(
+                    id("a"),
+                    BinaryOp::GtEq,
+                    infix(id("b"), BinaryOp::Shl, id("c")),
+                )),
+            ),
+            (
+                "a >> b - c;",
+                expr(infix(
+                    id("a"),
+                    BinaryOp::Shr,
+                    infix(id("b"), BinaryOp::Sub, id("c")),
+                )),
+            ),
+            (
+                "a + b * c;",
+                expr(infix(
+                    id("a"),
+                    BinaryOp::Add,
+                    infix(id("b"), BinaryOp::Mul, id("c")),
+                )),
+            ),
+            (
+                "a + b % c;",
+                expr(infix(
+                    id("a"),
+                    BinaryOp::Add,
+                    infix(id("b"), BinaryOp::Mod, id("c")),
+                )),
+            ),
+            (
+                "-a ** b;",
+                expr(prefix(
+                    UnaryOp::Minus,
+                    infix(id("a"), BinaryOp::Pow, id("b")),
+                )),
+            ),
+            (
+                "a - b - c;",
+                expr(infix(
+                    infix(id("a"), BinaryOp::Sub, id("b")),
+                    BinaryOp::Sub,
+                    id("c"),
+                )),
+            ),
+            (
+                "a ** b ** c;",
+                expr(infix(
+                    id("a"),
+                    BinaryOp::Pow,
+                    infix(id("b"), BinaryOp::Pow, id("c")),
+                )),
+            ),
+            ("a > b;", expr(infix(id("a"), BinaryOp::Gt, id("b")))),
+            ("a <= b;", expr(infix(id("a"), BinaryOp::LtEq, id("b")))),
+            ("a / b;", expr(infix(id("a"), BinaryOp::Div, id("b")))),
+            ("a * b;", expr(infix(id("a"), BinaryOp::Mul, id("b")))),
+            ("a >>> b;", expr(infix(id("a"), BinaryOp::UShr, id("b")))),
+            ("a >< b;", expr(infix(id("a"), BinaryOp::Substr, id("b")))),
+            (
+                "a >!< b;",
+                expr(infix(id("a"), BinaryOp::NotSubstr, id("b"))),
+            ),
+            ("a =~ b;", expr(infix(id("a"), BinaryOp::ReMatch, id("b")))),
+            (
+                "a !~ b;",
+                expr(infix(id("a"), BinaryOp::NotReMatch, id("b"))),
+            ),
+            (
+                "(a + b) * c;",
+                expr(infix(
+                    grouped(infix(id("a"), BinaryOp::Add, id("b"))),
+                    BinaryOp::Mul,
+                    id("c"),
+                )),
+            ),
+            // Assignments
+            ("a = 1;", expr(assign(id("a"), AssignOp::Assign, num(1)))),
+            ("a += 1;", expr(assign(id("a"), AssignOp::Add, num(1)))),
+            ("a -= 1;", expr(assign(id("a"), AssignOp::Sub, num(1)))),
+            ("a *= 1;", expr(assign(id("a"), AssignOp::Mul, num(1)))),
+            ("a /= 1;", expr(assign(id("a"), AssignOp::Div, num(1)))),
+            ("a %= 1;", expr(assign(id("a"), AssignOp::Mod, num(1)))),
+            ("a ^= 1;", expr(assign(id("a"), AssignOp::BitXor, num(1)))),
+            ("a <<= 1;", expr(assign(id("a"), AssignOp::Shl, num(1)))),
+            ("a >>= 1;", expr(assign(id("a"), AssignOp::Shr, num(1)))),
+            ("a >>>= 1;", expr(assign(id("a"), AssignOp::UShr, num(1)))),
+            (
+                "a = b = c;",
+                expr(assign(
+                    id("a"),
+                    AssignOp::Assign,
+                    assign(id("b"), AssignOp::Assign, id("c")),
+                )),
+            ),
+            (
+                "a[1] = b || c;",
+                expr(assign(
+                    index(id("a"), num(1)),
+                    AssignOp::Assign,
+                    infix(id("b"), BinaryOp::Or, id("c")),
+                )),
+            ),
+            // Calls and indexes
+            ("f();", expr(call("f", Vec::new()))),
+            ("f(a, 1);", expr(call("f", vec![id("a"), num(1)]))),
+            (
+                "f(1, a:b, c);",
+                expr(e(ExpressionKind::FunctionCall {
+                    ident: name("f"),
+                    args: vec![
+                        Arg::Anon(num(1)),
+                        Arg::Named(name("a"), id("b")),
+                        Arg::Anon(id("c")),
+                    ],
+                })),
+            ),
+            (
+                "a = -2147483648;",
+                expr(assign(
+                    id("a"),
+                    AssignOp::Assign,
+                    prefix(UnaryOp::Minus, num(2_147_483_648)),
+                )),
+            ),
+            (
+                "a = 0xFFFFFFFF;",
+                expr(assign(id("a"), AssignOp::Assign, num(0xFFFF_FFFF))),
+            ),
+            ("a[b][1];", expr(index(index(id("a"), id("b")), num(1)))),
+            ("f()[0];", expr(index(call("f", Vec::new()), num(0)))),
+            ("f() x 3;", {
+                s(StatementKind::Repeated {
+                    call: Box::new(call("f", Vec::new())),
+                    count: Box::new(num(3)),
+                })
+            }),
+            // Statements
+            ("{}", block(Vec::new())),
+            ("{ a; b; }", block(vec![expr(id("a")), expr(id("b"))])),
+            ("break;", s(StatementKind::Break)),
+            ("continue;", s(StatementKind::Continue)),
+            ("return;", s(StatementKind::Return { expr: None })),
+            (
+                "return a;",
+                s(StatementKind::Return {
+                    expr: Some(Box::new(id("a"))),
+                }),
+            ),
+            (
+                "include('a.inc');",
+                s(StatementKind::Include {
+                    path: "a.inc",
+                    path_span: Span::new(8, 15, 1, 9),
+                }),
+            ),
+            (
+                "local_var a, b = 1;",
+                s(StatementKind::Declaration {
+                    scope: VarScope::Local,
+                    vars: vec![
+                        VarDecl {
+                            ident: name("a"),
+                            init: None,
+                        },
+                        VarDecl {
+                            ident: name("b"),
+                            init: Some(Box::new(num(1))),
+                        },
+                    ],
+                }),
+            ),
+            (
+                "global_var g;",
+                s(StatementKind::Declaration {
+                    scope: VarScope::Global,
+                    vars: vec![VarDecl {
+                        ident: name("g"),
+                        init: None,
+                    }],
+                }),
+            ),
+            (
+                "if (a) b;",
+                s(StatementKind::If {
+                    cond: Box::new(id("a")),
+                    if_block: Box::new(expr(id("b"))),
+                    else_block: None,
+                }),
+            ),
+            (
+                "if (a) b; else if (c) {} else d;",
+                s(StatementKind::If {
+                    cond: Box::new(id("a")),
+                    if_block: Box::new(expr(id("b"))),
+                    else_block: Some(Box::new(s(StatementKind::If {
+                        cond: Box::new(id("c")),
+                        if_block: Box::new(block(Vec::new())),
+                        else_block: Some(Box::new(expr(id("d")))),
+                    }))),
+                }),
+            ),
+            (
+                "if (a) ; else b;",
+                s(StatementKind::If {
+                    cond: Box::new(id("a")),
+                    if_block: Box::new(s(StatementKind::Empty)),
+                    else_block: Some(Box::new(expr(id("b")))),
+                }),
+            ),
+            (
+                "while (a) a--;",
+                s(StatementKind::While {
+                    cond: Box::new(id("a")),
+                    block: Box::new(expr(postfix(id("a"), UnaryOp::Decr))),
+                }),
+            ),
+            (
+                "for (i = 0; i < 3; i++) {}",
+                s(StatementKind::For {
+                    init_expr: Some(Box::new(assign(id("i"), AssignOp::Assign, num(0)))),
+                    cond: Some(Box::new(infix(id("i"), BinaryOp::Lt, num(3)))),
+                    end_expr: Some(Box::new(postfix(id("i"), UnaryOp::Incr))),
+                    block: Box::new(block(Vec::new())),
+                }),
+            ),
+            (
+                "for (;;);",
+                s(StatementKind::For {
+                    init_expr: None,
+                    cond: None,
+                    end_expr: None,
+                    block: Box::new(s(StatementKind::Empty)),
+                }),
+            ),
+            (
+                "for (a, b;;) {}",
+                s(StatementKind::For {
+                    init_expr: Some(Box::new(e(ExpressionKind::Sequence(vec![
+                        id("a"),
+                        id("b"),
+                    ])))),
+                    cond: None,
+                    end_expr: None,
+                    block: Box::new(block(Vec::new())),
+                }),
+            ),
+            (
+                "foreach x (l) f(x);",
+                s(StatementKind::Foreach {
+                    var: name("x"),
+                    array: Box::new(id("l")),
+                    block: Box::new(expr(call("f", vec![id("x")]))),
+                }),
+            ),
+            (
+                "repeat a++; until a > 3;",
+                s(StatementKind::Repeat {
+                    block: Box::new(expr(postfix(id("a"), UnaryOp::Incr))),
+                    cond: Box::new(infix(id("a"), BinaryOp::Gt, num(3))),
+                }),
+            ),
+            (
+                "function f(a, b) { return _FCT_ANON_ARGS; }",
+                s(StatementKind::FunctionDef {
+                    ident: name("f"),
+                    params: vec![name("a"), name("b")],
+                    uses_anon_args: true,
+                    body: Box::new(block(vec![s(StatementKind::Return {
+                        expr: Some(Box::new(id("_FCT_ANON_ARGS"))),
+                    })])),
+                }),
+            ),
+        ];
+
+        for (code, stmt) in cases {
+            let stmts = Parser::new(code)
+                .parse()
+                .unwrap_or_else(|e| panic!("{}: {:?}", code, e));
+            assert_eq!(without_ident_spans(stmts), vec![stmt], "{}", code);
+        }
+    }
+
+    #[test]
+    fn trees_are_values() {
+        use std::collections::HashSet;
+
+        let stmts = Parser::new("a = 1; a = 1; b = 2;").parse().unwrap();
+        let stmts = without_spans(stmts);
+        let copy = stmts.clone();
+        assert_eq!(copy, stmts);
+
+        let distinct: HashSet<_> = stmts.iter().collect();
+        assert_eq!(distinct.len(), 2);
     }
 }
@@ -0,0 +1,684 @@
+//! Name resolution of the Nasl language.
+//!
+//! `resolve` builds the tree of the scopes of a file and binds every
+//! variable and every called or referenced function to its definition or
+//! to a builtin. The rules follow the interpreter:
+//!
+//! - Functions are global and can be called before they are defined.
+//!   Variables and functions do not share names.
+//! - The body of a function is a scope of its own, whose parent is the
+//!   global scope and not the scope of the code around the definition.
+//! - `local_var` declares variables in the innermost scope, which is a
+//!   block, a function body or the global scope. `global_var` always
+//!   declares them in the global scope.
+//! - Assigning to a variable that cannot be seen yet defines it in the
+//!   enclosing function, or in the global scope outside of functions. The
+//!   same holds for the variable of a `foreach` loop.
+//!
+//! Names that a file does not define itself can come from the files it
+//! includes, see `resolve_with_includes` and the `include` module, or be
+//! builtins of the interpreter.
+//!
+//! Uses are bound once the whole file has been seen, so a variable can be
+//! read before the assignment that defines it, e.g. in a loop. The code
+//! outside of functions is resolved before the bodies of the functions, so
+//! these can use global variables that are defined further down.
+
+use crate::ast::*;
+use crate::builtins;
+use crate::error::Diagnostic;
+use crate::include::FileId;
+use crate::span::Span;
+use crate::visit::{walk_expression, walk_statement, walk_statements, Visitor};
+use std::collections::{HashMap, HashSet};
+use std::sync::Arc;
+
+/// Collects the variables declared with `global_var`.
+#[derive(Default)]
+struct GlobalVars<'a>(Vec<Ident<&'a str>>);
+
+impl<'ast, 'a> Visitor<'ast, &'a str> for GlobalVars<'a> {
+    fn visit_statement(&mut self, stmt: &'ast Statement<&'a str>) {
+        if let StatementKind::Declaration {
+            scope: VarScope::Global,
+            vars,
+        } = &stmt.kind
+        {
+            self.0.extend(vars.iter().map(|var| var.ident));
+        }
+        walk_statement(self, stmt);
+    }
+}
+
+/// Index of a scope in a `ScopeTree`.
+#[derive(Debug, PartialEq, Eq, Hash, Clone, Copy)]
+pub struct ScopeId(u32);
+
+/// Index of a definition in a `ScopeTree`.
+#[derive(Debug, PartialEq, Eq, Hash, Clone, Copy)]
+pub struct DefId(u32);
+
+#[derive(Debug, PartialEq, Eq, Clone, Copy)]
+pub enum ScopeKind {
+    /// The top level of the file.
+    Global,
+    /// The body of a function.
+    Function,
+    /// A block, which holds the variables declared in it with `local_var`.
+    Block,
+}
+
+#[derive(Debug)]
+pub struct Scope<'a> {
+    pub kind: ScopeKind,
+    pub parent: Option<ScopeId>,
+    pub children: Vec<ScopeId>,
+    pub span: Span,
+    variables: HashMap<&'a str, DefId>,
+}
+
+impl<'a> Scope<'a> {
+    /// Variable `name` if it is defined in this very scope.
+    pub fn variable(&self, name: &str) -> Option<DefId> {
+        self.variables.get(name).copied()
+    }
+}
+
+#[derive(Debug, PartialEq, Eq, Clone, Copy)]
+pub enum DefKind {
+    Function,
+    Parameter,
+    /// Declared with `local_var` or `global_var`.
+    Declared(VarScope),
+    /// Defined by an assignment or as the variable of a `foreach` loop.
+    Assigned,
+}
+
+#[derive(Debug, PartialEq, Eq, Clone)]
+pub struct Definition<'a> {
+    pub name: &'a str,
+    pub kind: DefKind,
+    /// Location of the name.
+    pub span: Span,
+    pub scope: ScopeId,
+}
+
+/// What a name refers to.
+#[derive(Debug, PartialEq, Eq, Clone, Copy)]
+pub enum Binding {
+    Definition(DefId),
+    Included(IncludedDef),
+    Builtin,
+}
+
+/// A global definition of an included file.
+#[derive(Debug, PartialEq, Eq, Clone, Copy)]
+pub struct IncludedDef {
+    pub file: FileId,
+    pub kind: DefKind,
+    pub span: Span,
+}
+
+/// The functions and global variables of the files that a script includes.
+#[derive(Debug, Default, Clone)]
+pub struct SymbolTable {
+    functions: HashMap<Arc<str>, IncludedDef>,
+    variables: HashMap<Arc<str>, IncludedDef>,
+}
+
+impl SymbolTable {
+    pub fn new() -> Self {
+        SymbolTable::default()
+    }
+
+    pub fn function(&self, name: &str) -> Option<IncludedDef> {
+        self.functions.get(name).copied()
+    }
+
+    pub fn variable(&self, name: &str) -> Option<IncludedDef> {
+        self.variables.get(name).copied()
+    }
+
+    pub fn functions(&self) -> impl Iterator<Item = (&str, IncludedDef)> {
+        self.functions.iter().map(|(name, def)| (&**name, *def))
+    }
+
+    pub fn variables(&self) -> impl Iterator<Item = (&str, IncludedDef)> {
+        self.variables.iter().map(|(name, def)| (&**name, *def))
+    }
+
+    /// Adds `def` unless a definition of `name` is there already.
+    pub fn insert(&mut self, name: Arc<str>, def: IncludedDef) {
+        let names = if def.kind == DefKind::Function {
+            &mut self.functions
+        } else {
+            &mut self.variables
+        };
+        names.entry(name).or_insert(def);
+    }
+}
+
+/// Names that can be used without being defined in the file.
+#[derive(Debug, Default, Clone)]
+pub struct Builtins<'b> {
+    functions: HashSet<&'b str>,
+    variables: HashSet<&'b str>,
+}
+
+impl<'b> Builtins<'b> {
+    pub fn new() -> Self {
+        Builtins::default()
+    }
+
+    /// The functions and variables of the interpreter, see `builtins`.
+    pub fn nasl() -> Builtins<'static> {
+        Builtins {
+            functions: builtins::FUNCTIONS.iter().copied().collect(),
+            variables: builtins::VARIABLES.iter().copied().collect(),
+        }
+    }
+
+    pub fn add_function(&mut self, name: &'b str) {
+        self.functions.insert(name);
+    }
+
+    pub fn add_variable(&mut self, name: &'b str) {
+        self.variables.insert(name);
+    }
+
+    pub fn is_function(&self, name: &str) -> bool {
+        self.functions.contains(name)
+    }
+
+    pub fn is_variable(&self, name: &str) -> bool {
+        self.variables.contains(name)
+    }
+}
+
+/// The scopes and definitions of a file, and what its names refer to.
+#[derive(Debug)]
+pub struct ScopeTree<'a> {
+    scopes: Vec<Scope<'a>>,
+    definitions: Vec<Definition<'a>>,
+    functions: HashMap<&'a str, DefId>,
+    bindings: HashMap<Span, Binding>,
+}
+
+impl<'a> ScopeTree<'a> {
+    fn new(span: Span) -> Self {
+        ScopeTree {
+            scopes: vec![Scope {
+                kind: ScopeKind::Global,
+                parent: None,
+                children: Vec::new(),
+                span,
+                variables: HashMap::new(),
+            }],
+            definitions: Vec::new(),
+            functions: HashMap::new(),
+            bindings: HashMap::new(),
+        }
+    }
+
+    pub fn global(&self) -> ScopeId {
+        ScopeId(0)
+    }
+
+    pub fn scope(&self, id: ScopeId) -> &Scope<'a> {
+        &self.scopes[id.0 as usize]
+    }
+
+    pub fn definition(&self, id: DefId) -> &Definition<'a> {
+        &self.definitions[id.0 as usize]
+    }
+
+    pub fn definitions(&self) -> &[Definition<'a>] {
+        &self.definitions
+    }
+
+    /// Function `name` if it is defined in the file.
+    pub fn function(&self, name: &str) -> Option<DefId> {
+        self.functions.get(name).copied()
+    }
+
+    /// Variable `name` as seen from `scope`, which is looked up in `scope`
+    /// and then in the scopes around it.
+    pub fn lookup(&self, scope: ScopeId, name: &str) -> Option<DefId> {
+        let mut next = Some(scope);
+        while let Some(id) = next {
+            let scope = self.scope(id);
+            if let Some(def) = scope.variable(name) {
+                return Some(def);
+            }
+            next = scope.parent;
+        }
+        None
+    }
+
+    /// What the name at `span` refers to. This is the span of an
+    /// `Identifier` expression, of a whole function call for the called
+    /// function or of the name of a function reference.
+    pub fn binding(&self, span: Span) -> Option<Binding> {
+        self.bindings.get(&span).copied()
+    }
+
+    fn add_scope(&mut self, kind: ScopeKind, parent: ScopeId, span: Span) -> ScopeId {
+        let id = ScopeId(self.scopes.len() as u32);
+        self.scopes.push(Scope {
+            kind,
+            parent: Some(parent),
+            children: Vec::new(),
+            span,
+            variables: HashMap::new(),
+        });
+        self.scopes[parent.0 as usize].children.push(id);
+        id
+    }
+
+    fn define(&mut self, name: &'a str, kind: DefKind, span: Span, scope: ScopeId) -> DefId {
+        let id = DefId(self.definitions.len() as u32);
+        self.definitions.push(Definition {
+            name,
+            kind,
+            span,
+            scope,
+        });
+        if kind == DefKind::Function {
+            self.functions.insert(name, id);
+        } else {
+            self.scopes[scope.0 as usize].variables.insert(name, id);
+        }
+        id
+    }
+}
+
+/// Builds the scope tree of `stmts` and binds the names used in them.
+/// Names that are neither defined in `stmts` nor in `builtins` are
+/// reported.
+pub fn resolve<'a>(
+    stmts: &[Statement<&'a str>],
+    builtins: &Builtins,
+) -> (ScopeTree<'a>, Vec<Diagnostic>) {
+    resolve_with_includes(stmts, builtins, &SymbolTable::new())
+}
+
+/// Like `resolve`, but names can also refer to the definitions in
+/// `included`. The definitions of `stmts` come first, then the included
+/// ones and then the builtins.
+pub fn resolve_with_includes<'a>(
+    stmts: &[Statement<&'a str>],
+    builtins: &Builtins,
+    included: &SymbolTable,
+) -> (ScopeTree<'a>, Vec<Diagnostic>) {
+    let span = match (stmts.first(), stmts.last()) {
+        (Some(first), Some(last)) => first.span.to(last.span),
+        _ => Span::default(),
+    };
+    let mut resolver = Resolver {
+        builtins,
+        included,
+        tree: ScopeTree::new(span),
+        current: ScopeId(0),
+        functions: Vec::new(),
+        uses: Vec::new(),
+        errors: Vec::new(),
+    };
+
+    // Functions may use global variables that are declared in other
+    // functions.
+    let mut global_vars = GlobalVars::default();
+    walk_statements(&mut global_vars, stmts);
+    for ident in global_vars.0 {
+        resolver.declare_global(ident);
+    }
+
+    walk_statements(&mut resolver, stmts);
+    let mut i = 0;
+    while let Some(function) = resolver.functions.get(i) {
+        resolver.function_body(function);
+        i += 1;
+    }
+    resolver.finish()
+}
+
+/// A name that is used in a scope.
+struct Use<'a> {
+    name: &'a str,
+    span: Span,
+    scope: ScopeId,
+    function: bool,
+}
+
+struct Resolver<'ast, 'a, 'b> {
+    builtins: &'b Builtins<'b>,
+    included: &'b SymbolTable,
+    tree: ScopeTree<'a>,
+    current: ScopeId,
+    /// Function definitions whose bodies are resolved after the code
+    /// outside of functions.
+    functions: Vec<&'ast Statement<&'a str>>,
+    uses: Vec<Use<'a>>,
+    errors: Vec<Diagnostic>,
+}
+
+impl<'ast, 'a, 'b> Resolver<'ast, 'a, 'b> {
+    fn enter(&mut self, kind: ScopeKind, span: Span) {
+        self.current = self.tree.add_scope(kind, self.current, span);
+    }
+
+    fn leave(&mut self) {
+        self.current = self.tree.scope(self.current).parent.unwrap();
+    }
+
+    fn declare_global(&mut self, ident: Ident<&'a str>) {
+        let global = self.tree.global();
+        if self.tree.scope(global).variable(ident.name).is_none() {
+            let kind = DefKind::Declared(VarScope::Global);
+            self.tree.define(ident.name, kind, ident.span, global);
+        }
+    }
+
+    /// Defines `name` in the enclosing function, or the global scope, unless
+    /// it can be seen already.
+    fn assign(&mut self, name: &'a str, span: Span) {
+        if self.tree.lookup(self.current, name).is_some()
+            || self.included.variable(name).is_some()
+            || self.builtins.is_variable(name)
+        {
+            return;
+        }
+        let mut scope = self.current;
+        while self.tree.scope(scope).kind == ScopeKind::Block {
+            scope = self.tree.scope(scope).parent.unwrap();
+        }
+        self.tree.define(name, DefKind::Assigned, span, scope);
+    }
+
+    fn function_body(&mut self, function: &'ast Statement<&'a str>) {
+        if let StatementKind::FunctionDef { params, body, .. } = &function.kind {
+            self.current = self.tree.global();
+            self.enter(ScopeKind::Function, function.span);
+            for param in params {
+                if self.tree.scope(self.current).variable(param.name).is_none() {
+                    self.tree
+                        .define(param.name, DefKind::Parameter, param.span, self.current);
+                }
+            }
+            match &body.kind {
+                StatementKind::Block { statements } => walk_statements(self, statements),
+                _ => self.visit_statement(body),
+            }
+            self.leave();
+        }
+    }
+
+    /// Binds the uses, now that every definition is known.
+    fn finish(mut self) -> (ScopeTree<'a>, Vec<Diagnostic>) {
+        for use_ in &self.uses {
+            let def = if use_.function {
+                self.tree.function(use_.name)
+            } else {
+                self.tree.lookup(use_.scope, use_.name)
+            };
+            let included = if use_.function {
+                self.included.function(use_.name)
+            } else {
+                self.included.variable(use_.name)
+            };
+            let binding = match (def, included) {
+                (Some(def), _) => Binding::Definition(def),
+                (None, Some(def)) => Binding::Included(def),
+                _ if use_.function && self.builtins.is_function(use_.name) => Binding::Builtin,
+                _ if !use_.function && self.builtins.is_variable(use_.name) => Binding::Builtin,
+                _ => {
+                    let what = if use_.function {
+                        "function"
+                    } else {
+                        "variable"
+                    };
+                    self.errors.push(Diagnostic::resolve(
+                        format!("undefined {} `{}`", what, use_.name),
+                        use_.span,
+                    ));
+                    continue;
+                }
+            };
+            self.tree.bindings.insert(use_.span, binding);
+        }
+        self.errors.sort_by_key(|e| e.span.start);
+        (self.tree, self.errors)
+    }
+}
+
+/// The variable that is assigned to by assigning to `target`, `a` in
+/// `a[1][2] = x`, and its span.
+fn assigned_variable<'a>(target: &Expression<&'a str>) -> Option<(&'a str, Span)> {
+    match &target.kind {
+        ExpressionKind::Identifier(name) => Some((name, target.span)),
+        ExpressionKind::Index { expr, .. } => assigned_variable(expr),
+        _ => None,
+    }
+}
+
+impl<'ast, 'a, 'b> Visitor<'ast, &'a str> for Resolver<'ast, 'a, 'b> {
+    fn visit_statement(&mut self, stmt: &'ast Statement<&'a str>) {
+        match &stmt.kind {
+            StatementKind::FunctionDef { ident, .. } => {
+                if self.tree.function(ident.name).is_some() {
+                    self.errors.push(Diagnostic::resolve(
+                        format!("function `{}` is already defined", ident.name),
+                        ident.span,
+                    ));
+                } else {
+                    let global = self.tree.global();
+                    self.tree
+                        .define(ident.name, DefKind::Function, ident.span, global);
+                    self.functions.push(stmt);
+                }
+            }
+            StatementKind::Block { statements } => {
+                self.enter(ScopeKind::Block, stmt.span);
+                walk_statements(self, statements);
+                self.leave();
+            }
+            StatementKind::Declaration { scope, vars } => {
+                for var in vars {
+                    if let Some(init) = &var.init {
+                        self.visit_expression(init);
+                    }
+                    match scope {
+                        VarScope::Global => self.declare_global(var.ident),
+                        VarScope::Local => {
+                            let ident = var.ident;
+                            if self.tree.scope(self.current).variable(ident.name).is_none() {
+                                let kind = DefKind::Declared(VarScope::Local);
+                                self.tree.define(ident.name, kind, ident.span, self.current);
+                            }
+                        }
+                    }
+                }
+            }
+            StatementKind::Foreach { var, .. } => {
+                self.assign(var.name, var.span);
+                walk_statement(self, stmt);
+            }
+            _ => walk_statement(self, stmt),
+        }
+    }
+
+    fn visit_expression(&mut self, expr: &'ast Expression<&'a str>) {
+        let used = match &expr.kind {
+            ExpressionKind::Identifier(name) => Some((*name, expr.span, false)),
+            ExpressionKind::FunctionCall { ident, .. } => Some((ident.name, ident.span, true)),
+            ExpressionKind::FunctionRef(ident) => Some((ident.name, ident.span, true)),
+            ExpressionKind::Assign {
+                target,
+                op: AssignOp::Assign,
+                ..
+            } => {
+                if let Some((name, span)) = assigned_variable(target) {
+                    self.assign(name, span);
+                }
+                None
+            }
+            _ => None,
+        };
+        if let Some((name, span, function)) = used {
+            self.uses.push(Use {
+                name,
+                span,
+                scope: self.current,
+                function,
+            });
+        }
+        walk_expression(self, expr);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::parser::Parser;
+    use crate::span::Span;
+
+    /// Span of `name` in the first `context` of `code`.
+    fn span_in(code: &str, context: &str, name: &str) -> Span {
+        let start = code.find(context).unwrap() + context.find(name).unwrap();
+        let line_start = code[..start].rfind('\n').map_or(0, |i| i + 1);
+        let line = code[..start].matches('\n').count() + 1;
+        Span::new(start, start + name.len(), line, start - line_start + 1)
+    }
+
+    fn definition<'a>(tree: &'a ScopeTree, span: Span) -> &'a Definition<'a> {
+        match tree.binding(span) {
+            Some(Binding::Definition(def)) => tree.definition(def),
+            binding => panic!("{:?} is bound to {:?}", span, binding),
+        }
+    }
+
+    #[test]
+    fn scopes() {
+        let code = "x = 1;\n\
+            function f(a) {\n\
+            \x20 local_var y;\n\
+            \x20 if (a) {\n\
+            \x20   local_var z;\n\
+            \x20   z = a + x + y;\n\
+            \x20 }\n\
+            \x20 w[0] = z;\n\
+            \x20 return w;\n\
+            }\n\
+            foreach item (make_list(1, 2)) display(item);\n\
+            f(a:x);\n";
+        let stmts = Parser::new(code).parse().unwrap();
+        let (tree, errors) = resolve(&stmts, &Builtins::nasl());
+        assert_eq!(
+            errors,
+            vec![Diagnostic::resolve(
+                "undefined variable `z`",
+                span_in(code, "w[0] = z", "z")
+            )]
+        );
+
+        let global = tree.scope(tree.global());
+        assert_eq!(global.kind, ScopeKind::Global);
+        assert_eq!(global.children.len(), 1);
+        let function = tree.scope(global.children[0]);
+        assert_eq!(function.kind, ScopeKind::Function);
+        assert_eq!(function.parent, Some(tree.global()));
+        assert_eq!(function.children.len(), 1);
+        let block = tree.scope(function.children[0]);
+        assert_eq!(block.kind, ScopeKind::Block);
+        assert!(block.variable("z").is_some());
+
+        let sum = "a + x + y";
+        let a = definition(&tree, span_in(code, sum, "a"));
+        assert_eq!(a.kind, DefKind::Parameter);
+        assert_eq!(a.span, span_in(code, "f(a)", "a"));
+        let x = definition(&tree, span_in(code, sum, "x"));
+        assert_eq!((x.kind, x.scope), (DefKind::Assigned, tree.global()));
+        assert_eq!(x.span, span_in(code, "x = 1", "x"));
+        let y = definition(&tree, span_in(code, sum, "y"));
+        assert_eq!(y.kind, DefKind::Declared(VarScope::Local));
+        assert_eq!(y.scope, global.children[0]);
+        let w = definition(&tree, span_in(code, "return w", "w"));
+        assert_eq!((w.kind, w.scope), (DefKind::Assigned, global.children[0]));
+        assert_eq!(w.span, span_in(code, "w[0]", "w"));
+        let item = definition(&tree, span_in(code, "display(item)", "item"));
+        assert_eq!((item.kind, item.scope), (DefKind::Assigned, tree.global()));
+        assert_eq!(item.span, span_in(code, "foreach item", "item"));
+
+        let f = definition(&tree, span_in(code, "f(a:x)", "f"));
+        assert_eq!((f.name, f.kind), ("f", DefKind::Function));
+        assert_eq!(f.span, span_in(code, "f(a) {", "f"));
+        assert_eq!(
+            tree.binding(span_in(code, "display(item)", "display")),
+            Some(Binding::Builtin)
+        );
+    }
+
+    #[test]
+    fn uses_before_definitions() {
+        let code = "function f() {\n\
+            \x20 while (TRUE) { if (done) break; done = g(); }\n\
+            \x20 global_var shared;\n\
+            \x20 return later;\n\
+            }\n\
+            function g() { return shared; }\n\
+            later = f();\n";
+        let stmts = Parser::new(code).parse().unwrap();
+        let (tree, errors) = resolve(&stmts, &Builtins::new());
+        assert_eq!(errors, vec![]);
+        let done = definition(&tree, span_in(code, "if (done)", "done"));
+        assert_eq!(done.span, span_in(code, "done = g()", "done"));
+        let later = definition(&tree, span_in(code, "return later", "later"));
+        assert_eq!(later.scope, tree.global());
+        let shared = definition(&tree, span_in(code, "return shared", "shared"));
+        assert_eq!(shared.kind, DefKind::Declared(VarScope::Global));
+    }
+
+    #[test]
+    fn undefined_names() {
+        let code = "function f() { return g() + @h + v; }\nfunction f() {}\nf();";
+        let stmts = Parser::new(code).parse().unwrap();
+        let (_, errors) = resolve(&stmts, &Builtins::new());
+        assert_eq!(
+            errors,
+            vec![
+                Diagnostic::resolve("undefined function `g`", span_in(code, "g()", "g")),
+                Diagnostic::resolve("undefined function `h`", span_in(code, "@h", "h")),
+                Diagnostic::resolve("undefined variable `v`", span_in(code, "v;", "v")),
+                Diagnostic::resolve(
+                    "function `f` is already defined",
+                    span_in(code, "f() {}", "f")
+                ),
+            ]
+        );
+
+        let code = "x = 1; foo(a: x, b: 2);";
+        let stmts = Parser::new(code).parse().unwrap();
+        let (_, errors) = resolve(&stmts, &Builtins::new());
+        assert_eq!(
+            errors,
+            vec![Diagnostic::resolve(
+                "undefined function `foo`",
+                Span::new(7, 10, 1, 8)
+            )]
+        );
+    }
+
+    #[test]
+    fn plugin() {
+        let code = include_str!("../tests/golden/plugin.nasl");
+        let stmts = Parser::new(code).parse().unwrap();
+        let (_, errors) = resolve(&stmts, &Builtins::nasl());
+        let messages: Vec<_> = errors.iter().map(|e| e.message.as_str()).collect();
+        assert_eq!(
+            messages,
+            [
+                "undefined function `http_keepalive_send_recv`",
+                "undefined function `http_get_port`"
+            ]
+        );
+    }
+}
@@ -0,0 +1,269 @@
+//! Compact S-expression dump of the AST.
+//!
+//! Every node is printed as `(kind children...)`, e.g. `if (a + 23 * b) {}`
+//! becomes `(if (+ a (* 23 b)) (block))`. Names, numbers and literals are
+//! printed as written in the source. Statements that contain other
+//! statements put each of them on its own, indented line, expressions are
+//! kept on one line. Spans are left out, so the dump only changes when the
+//! shape of the tree changes.
+
+use crate::ast::*;
+use std::fmt::Write;
+
+/// Dumps a whole file, one top level statement per line.
+pub fn to_sexp<S: AsRef<str>>(stmts: &[Statement<S>]) -> String {
+    let mut out = String::new();
+    for stmt in stmts {
+        write_statement(&mut out, stmt, 0);
+        out.push('\n');
+    }
+    out
+}
+
+pub fn statement_to_sexp<S: AsRef<str>>(stmt: &Statement<S>) -> String {
+    let mut out = String::new();
+    write_statement(&mut out, stmt, 0);
+    out
+}
+
+pub fn expression_to_sexp<S: AsRef<str>>(expr: &Expression<S>) -> String {
+    let mut out = String::new();
+    write_expression(&mut out, expr);
+    out
+}
+
+/// Writes a child statement on a new line.
+fn write_child<S: AsRef<str>>(out: &mut String, stmt: &Statement<S>, indent: usize) {
+    new_line(out, indent);
+    write_statement(out, stmt, indent);
+}
+
+fn new_line(out: &mut String, indent: usize) {
+    out.push('\n');
+    for _ in 0..indent {
+        out.push_str("  ");
+    }
+}
+
+fn write_statement<S: AsRef<str>>(out: &mut String, stmt: &Statement<S>, indent: usize) {
+    let inner = indent + 1;
+    match &stmt.kind {
+        StatementKind::If {
+            cond,
+            if_block,
+            else_block,
+        } => {
+            out.push_str("(if ");
+            write_expression(out, cond);
+            write_child(out, if_block, inner);
+            if let Some(else_block) = else_block {
+                write_child(out, else_block, inner);
+            }
+            out.push(')');
+        }
+        StatementKind::While { cond, block } => {
+            out.push_str("(while ");
+            write_expression(out, cond);
+            write_child(out, block, inner);
+            out.push(')');
+        }
+        StatementKind::For {
+            init_expr,
+            cond,
+            end_expr,
+            block,
+        } => {
+            out.push_str("(for");
+            for expr in &[init_expr, cond, end_expr] {
+                out.push(' ');
+                match expr {
+                    Some(expr) => write_expression(out, expr),
+                    None => out.push_str("()"),
+                }
+            }
+            write_child(out, block, inner);
+            out.push(')');
+        }
+        StatementKind::Foreach { var, array, block } => {
+            write!(out, "(foreach {} ", var.name.as_ref()).unwrap();
+            write_expression(out, array);
+            write_child(out, block, inner);
+            out.push(')');
+        }
+        StatementKind::Repeat { block, cond } => {
+            out.push_str("(repeat");
+            write_child(out, block, inner);
+            new_line(out, inner);
+            write_expression(out, cond);
+            out.push(')');
+        }
+        StatementKind::FunctionDef {
+            ident,
+            params,
+            body,
+            ..
+        } => {
+            write!(out, "(function {} (", ident.name.as_ref()).unwrap();
+            for (i, param) in params.iter().enumerate() {
+                if i > 0 {
+                    out.push(' ');
+                }
+                out.push_str(param.name.as_ref());
+            }
+            out.push(')');
+            write_child(out, body, inner);
+            out.push(')');
+        }
+        StatementKind::Declaration { scope, vars } => {
+            out.push_str(match scope {
+                VarScope::Local => "(local_var",
+                VarScope::Global => "(global_var",
+            });
+            for var in vars {
+                out.push(' ');
+                match &var.init {
+                    Some(init) => {
+                        write!(out, "(= {} ", var.ident.name.as_ref()).unwrap();
+                        write_expression(out, init);
+                        out.push(')');
+                    }
+                    None => out.push_str(var.ident.name.as_ref()),
+                }
+            }
+            out.push(')');
+        }
+        StatementKind::Include { path, .. } => {
+            write!(out, "(include \"{}\")", path.as_ref()).unwrap();
+        }
+        StatementKind::Return { expr } => {
+            out.push_str("(return");
+            if let Some(expr) = expr {
+                out.push(' ');
+                write_expression(out, expr);
+            }
+            out.push(')');
+        }
+        StatementKind::Break => out.push_str("(break)"),
+        StatementKind::Continue => out.push_str("(continue)"),
+        StatementKind::Empty => out.push_str("(empty)"),
+        StatementKind::Block { statements } => {
+            out.push_str("(block");
+            for stmt in statements {
+                write_child(out, stmt, inner);
+            }
+            out.push(')');
+        }
+        StatementKind::Expression(expr) => write_expression(out, expr),
+        StatementKind::Repeated { call, count } => {
+            out.push_str("(x ");
+            write_expression(out, call);
+            out.push(' ');
+            write_expression(out, count);
+            out.push(')');
+        }
+    }
+}
+
+fn write_expression<S: AsRef<str>>(out: &mut String, expr: &Expression<S>) {
+    match &expr.kind {
+        ExpressionKind::Identifier(name) => out.push_str(name.as_ref()),
+        ExpressionKind::Number(value) => write!(out, "{}", value).unwrap(),
+        ExpressionKind::String(value) => write!(out, "\"{}\"", value.as_ref()).unwrap(),
+        ExpressionKind::Data(value) => write!(out, "'{}'", value.as_ref()).unwrap(),
+        ExpressionKind::Boolean(true) => out.push_str("TRUE"),
+        ExpressionKind::Boolean(false) => out.push_str("FALSE"),
+        ExpressionKind::Null => out.push_str("NULL"),
+        ExpressionKind::Prefix { op, expr } => {
+            write!(out, "({} ", op).unwrap();
+            write_expression(out, expr);
+            out.push(')');
+        }
+        ExpressionKind::Postfix { expr, op } => {
+            // `post` tells `a++` from `++a`.
+            write!(out, "(post{} ", op).unwrap();
+            write_expression(out, expr);
+            out.push(')');
+        }
+        ExpressionKind::Infix { l_expr, op, r_expr } => {
+            write!(out, "({} ", op).unwrap();
+            write_expression(out, l_expr);
+            out.push(' ');
+            write_expression(out, r_expr);
+            out.push(')');
+        }
+        ExpressionKind::Assign { target, op, expr } => {
+            write!(out, "({} ", op).unwrap();
+            write_expression(out, target);
+            out.push(' ');
+            write_expression(out, expr);
+            out.push(')');
+        }
+        ExpressionKind::Index { expr, index } => {
+            out.push_str("(index ");
+            write_expression(out, expr);
+            out.push(' ');
+            write_expression(out, index);
+            out.push(')');
+        }
+        ExpressionKind::Grouped(expr) => {
+            out.push_str("(group ");
+            write_expression(out, expr);
+            out.push(')');
+        }
+        ExpressionKind::Sequence(exprs) => {
+            out.push_str("(seq");
+            for expr in exprs {
+                out.push(' ');
+                write_expression(out, expr);
+            }
+            out.push(')');
+        }
+        ExpressionKind::FunctionRef(ident) => write!(out, "@{}", ident.name.as_ref()).unwrap(),
+        ExpressionKind::FunctionCall { ident, args } => {
+            write!(out, "(call {}", ident.name.as_ref()).unwrap();
+            for arg in args {
+                out.push(' ');
+                if let Arg::Named(name, _) = arg {
+                    write!(out, "{}:", name.name.as_ref()).unwrap();
+                }
+                write_expression(out, arg.value());
+            }
+            out.push(')');
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::parser::Parser;
+
+    fn sexp(code: &str) -> String {
+        to_sexp(&Parser::new(code).parse().unwrap())
+    }
+
+    #[test]
+    fn expressions() {
+        assert_eq!(
+            sexp("if (a + 23 * b) {} else {}"),
+            "(if (+ a (* 23 b))\n  (block)\n  (block))\n"
+        );
+        assert_eq!(
+            sexp("x[i++] += -f(1, port:p + 1) ** @g;"),
+            "(+= (index x (post++ i)) (- (** (call f 1 port:(+ p 1)) @g)))\n"
+        );
+        assert_eq!(
+            sexp("a = (\"s\" + 'd\\n') >< NULL;"),
+            "(= a (>< (group (+ \"s\" 'd\\n')) NULL))\n"
+        );
+    }
+
+    #[test]
+    fn statements() {
+        let code = "function f(a) { local_var b = 1, c; for (;;) { break; } return; }";
+        assert_eq!(
+            sexp(code),
+            "(function f (a)\n  (block\n    (local_var (= b 1) c)\n    (for () () ()\n      (block\n        (break)))\n    (return)))\n"
+        );
+    }
+}
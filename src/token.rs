@@ -1,4 +1,7 @@
-#[derive(Debug, PartialEq, Eq, Hash)]
+use crate::span::Span;
+use std::fmt;
+
+#[derive(Debug, PartialEq, Eq, Hash, Clone, Copy)]
 pub enum TokenType {
     /* Operators */
     Plus,     // +
@@ -19,6 +22,7 @@ pub enum TokenType {
     BwAnd,    // &
     BwOr,     // |
     BwXor,    // ^
+    BwNot,    // ~
     PlusEq,   // +=
     MinusEq,  // -=
     DivEq,    // /=
@@ -30,6 +34,7 @@ pub enum TokenType {
     ShrEq,    // >>=
     ShlEq,    // <<=
     Ushr,     // >>>
+    UshrEq,   // >>>=
     Incr,     // ++
     Decr,     // --
     Pow,      // **
@@ -38,6 +43,8 @@ pub enum TokenType {
     ReMatch,  // =~
     NreMatch, // !~
 
+    At, // @
+
     If,
     Else,
     Lparan,
@@ -59,6 +66,10 @@ pub enum TokenType {
 
     Eof,
     Comment,
+    Whitespace,
+    /// Input that the lexer skipped because of an error. Only syntax trees
+    /// have such tokens.
+    Error,
 
     Function,
     Null,
@@ -77,12 +88,114 @@ pub enum TokenType {
     Until,
 }
 
+impl fmt::Display for TokenType {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let s = match self {
+            TokenType::Plus => "`+`",
+            TokenType::Minus => "`-`",
+            TokenType::Mul => "`*`",
+            TokenType::Div => "`/`",
+            TokenType::Mod => "`%`",
+            TokenType::Assign => "`=`",
+            TokenType::Equ => "`==`",
+            TokenType::Nequ => "`!=`",
+            TokenType::And => "`&&`",
+            TokenType::Or => "`||`",
+            TokenType::Gt => "`>`",
+            TokenType::Lt => "`<`",
+            TokenType::Gte => "`>=`",
+            TokenType::Lte => "`<=`",
+            TokenType::Not => "`!`",
+            TokenType::BwAnd => "`&`",
+            TokenType::BwOr => "`|`",
+            TokenType::BwXor => "`^`",
+            TokenType::BwNot => "`~`",
+            TokenType::PlusEq => "`+=`",
+            TokenType::MinusEq => "`-=`",
+            TokenType::DivEq => "`/=`",
+            TokenType::ModEq => "`%=`",
+            TokenType::MulEq => "`*=`",
+            TokenType::XorEq => "`^=`",
+            TokenType::Shr => "`>>`",
+            TokenType::Shl => "`<<`",
+            TokenType::ShrEq => "`>>=`",
+            TokenType::ShlEq => "`<<=`",
+            TokenType::Ushr => "`>>>`",
+            TokenType::UshrEq => "`>>>=`",
+            TokenType::Incr => "`++`",
+            TokenType::Decr => "`--`",
+            TokenType::Pow => "`**`",
+            TokenType::Substr => "`><`",
+            TokenType::NSUBSTR => "`>!<`",
+            TokenType::ReMatch => "`=~`",
+            TokenType::NreMatch => "`!~`",
+            TokenType::At => "`@`",
+            TokenType::If => "`if`",
+            TokenType::Else => "`else`",
+            TokenType::Lparan => "`(`",
+            TokenType::Rparan => "`)`",
+            TokenType::Lbrace => "`{`",
+            TokenType::Rbrace => "`}`",
+            TokenType::Lbracket => "`[`",
+            TokenType::Rbracket => "`]`",
+            TokenType::SemiColon => "`;`",
+            TokenType::Comma => "`,`",
+            TokenType::Ident => "identifier",
+            TokenType::PureStr => "data",
+            TokenType::ImpureStr => "string",
+            TokenType::Colon => "`:`",
+            TokenType::Num => "number",
+            TokenType::Eof => "end of file",
+            TokenType::Comment => "comment",
+            TokenType::Whitespace => "whitespace",
+            TokenType::Error => "invalid input",
+            TokenType::Function => "`function`",
+            TokenType::Null => "`NULL`",
+            TokenType::True => "`TRUE`",
+            TokenType::False => "`FALSE`",
+            TokenType::Return => "`return`",
+            TokenType::For => "`for`",
+            TokenType::While => "`while`",
+            TokenType::Break => "`break`",
+            TokenType::Continue => "`continue`",
+            TokenType::Foreach => "`foreach`",
+            TokenType::Include => "`include`",
+            TokenType::LocalVar => "`local_var`",
+            TokenType::GlobalVar => "`global_var`",
+            TokenType::Repeat => "`repeat`",
+            TokenType::Until => "`until`",
+        };
+        f.write_str(s)
+    }
+}
+
+#[derive(Debug, PartialEq, Eq, Clone, Copy)]
 pub struct Token<'a> {
     pub literal: &'a str,
     pub ty: TokenType,
+    pub span: Span,
+}
+
+/// Tokens whose literal tells more than their type are shown with the
+/// literal, e.g. identifier `foo`.
+impl<'a> fmt::Display for Token<'a> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self.ty {
+            TokenType::Ident | TokenType::Num => write!(f, "{} `{}`", self.ty, self.literal),
+            _ => write!(f, "{}", self.ty),
+        }
+    }
 }
 
 impl<'a> Token<'a> {
+    pub fn new(ty: TokenType, literal: &'a str) -> Self {
+        Token {
+            literal,
+            ty,
+            span: Span::default(),
+        }
+    }
+
     pub fn from_ident(literal: &'a str) -> Self {
         let ty = match literal {
             "if" => TokenType::If,
@@ -104,11 +217,11 @@ impl<'a> Token<'a> {
             "until" => TokenType::Until,
             _ => TokenType::Ident,
         };
-        TokenType { literal, ty }
+        Token::new(ty, literal)
     }
 
     // TODO: This only supports operators
-    pub fn from_str(literal: &'a str) -> Self {
+    pub fn from_op(literal: &'a str) -> Self {
         let ty = match literal {
             "+" => TokenType::Plus,
             "-" => TokenType::Minus,
@@ -128,6 +241,7 @@ impl<'a> Token<'a> {
             "&" => TokenType::BwAnd,
             "|" => TokenType::BwOr,
             "^" => TokenType::BwXor,
+            "~" => TokenType::BwNot,
             "+=" => TokenType::PlusEq,
             "-=" => TokenType::MinusEq,
             "/=" => TokenType::DivEq,
@@ -139,6 +253,7 @@ impl<'a> Token<'a> {
             ">>=" => TokenType::ShrEq,
             "<<=" => TokenType::ShlEq,
             ">>>" => TokenType::Ushr,
+            ">>>=" => TokenType::UshrEq,
             "++" => TokenType::Incr,
             "--" => TokenType::Decr,
             "**" => TokenType::Pow,
@@ -146,8 +261,27 @@ impl<'a> Token<'a> {
             ">!<" => TokenType::NSUBSTR,
             "=~" => TokenType::ReMatch,
             "!~" => TokenType::NreMatch,
-            _ => panic!("Unknown token {}", data),
+            _ => panic!("Unknown token {}", literal),
         };
-        TokenType { literal, ty }
+        Token::new(ty, literal)
+    }
+
+    /// Value of a `Num` token.
+    ///
+    /// Numbers starting with `0x` are hexadecimal, other numbers starting
+    /// with `0` are octal.
+    pub fn num_value(&self) -> Result<i64, String> {
+        let literal = self.literal;
+        if let Some(hex) = literal.strip_prefix("0x") {
+            i64::from_str_radix(hex, 16)
+                .map_err(|e| format!("Error occured while parsing hexadecimal number {}", e))
+        } else if literal.len() > 1 && literal.starts_with('0') {
+            i64::from_str_radix(literal, 8)
+                .map_err(|e| format!("Error occured while parsing octal number {}", e))
+        } else {
+            literal
+                .parse::<i64>()
+                .map_err(|_| format!("{} is not a valid number", literal))
+        }
     }
 }
@@ -0,0 +1,714 @@
+//! Turns the AST back into Nasl source.
+//!
+//! The output is canonical: two spaces of indentation, opening braces on the
+//! line of their statement, spaces around binary and assignment operators
+//! and one statement per line. Parentheses are only added where the
+//! precedence of the operators requires them, the ones of the source are
+//! kept as `Grouped` expressions. Parsing the output therefore gives the
+//! same tree again, apart from the spans.
+//!
+//! Numbers are printed in decimal and include paths in double quotes. The
+//! arguments of a call are printed in the order in which they are written.
+//!
+//! `to_nasl_with_comments` additionally takes the source of the tree and
+//! keeps its comments, single empty lines between statements and the
+//! spelling of numbers and include paths.
+
+use crate::ast::*;
+use crate::parser::Precedence;
+use crate::span::Span;
+use std::fmt::Write;
+
+/// Prints a whole file, one top level statement per line.
+pub fn to_nasl<S: AsRef<str>>(stmts: &[Statement<S>]) -> String {
+    let mut printer = Printer::new(None, &[]);
+    printer.statements(stmts, 0);
+    printer.out
+}
+
+/// Prints a whole file that was parsed from `source`, together with the
+/// `comments` found by the lexer.
+///
+/// Every comment is printed before the token that follows it in the source.
+/// Comments on their own line stay on their own line, comments after a
+/// statement stay on its line. A comment inside of a statement ends the line
+/// there and the statement continues on the next line, indented once more.
+pub fn to_nasl_with_comments<S: AsRef<str>>(
+    stmts: &[Statement<S>],
+    source: &str,
+    comments: &[Span],
+) -> String {
+    let mut printer = Printer::new(Some(source), comments);
+    printer.statements(stmts, 0);
+    printer.comments_before(usize::MAX, 0);
+    printer.out
+}
+
+pub fn statement_to_nasl<S: AsRef<str>>(stmt: &Statement<S>) -> String {
+    let mut printer = Printer::new(None, &[]);
+    printer.statement(stmt, 0);
+    printer.out
+}
+
+pub fn expression_to_nasl<S: AsRef<str>>(expr: &Expression<S>) -> String {
+    let mut printer = Printer::new(None, &[]);
+    printer.expression(expr);
+    printer.out
+}
+
+struct Printer<'s> {
+    out: String,
+    source: Option<&'s str>,
+    comments: &'s [Span],
+    // Index of the first comment that has not been printed yet.
+    next_comment: usize,
+    // End of the last statement or comment that has been printed.
+    last_end: usize,
+    // End of the last node of the source that has been printed.
+    pos: usize,
+    // Indentation of the statement that is being printed.
+    line_indent: usize,
+}
+
+/// Precedence of the operator at the root of `expr`, the operands of an
+/// operator with a higher precedence need to be put into parentheses.
+fn precedence<S>(expr: &Expression<S>) -> Precedence {
+    match &expr.kind {
+        ExpressionKind::Sequence(_) => Precedence::Comma,
+        ExpressionKind::Assign { .. } => Precedence::Assignment,
+        ExpressionKind::Infix { op, .. } => Precedence::of_binary(*op),
+        ExpressionKind::Prefix { .. } => Precedence::Prefix,
+        // Printed with a `-`, which reads as prefix operator.
+        ExpressionKind::Number(value) if *value < 0 => Precedence::Prefix,
+        _ => Precedence::Postfix,
+    }
+}
+
+impl<'s> Printer<'s> {
+    fn new(source: Option<&'s str>, comments: &'s [Span]) -> Self {
+        Printer {
+            out: String::new(),
+            source,
+            comments,
+            next_comment: 0,
+            last_end: 0,
+            pos: 0,
+            line_indent: 0,
+        }
+    }
+
+    fn indent(&mut self, indent: usize) {
+        for _ in 0..indent {
+            self.out.push_str("  ");
+        }
+    }
+
+    /// The source of `span`, if it is known.
+    fn source_of(&self, span: Span) -> Option<&'s str> {
+        self.source?.get(span.start..span.end)
+    }
+
+    /// Whether the source has an empty line between the last printed node
+    /// and `start`.
+    fn empty_line_before(&self, start: usize) -> bool {
+        self.source
+            .and_then(|source| source.get(self.last_end..start))
+            .is_some_and(|between| between.matches('\n').count() > 1)
+    }
+
+    /// Ends the current line with an empty line, unless there is one
+    /// already or the line opens a block.
+    fn empty_line(&mut self) {
+        if !self.out.is_empty() && !self.out.ends_with("\n\n") && !self.out.ends_with("{\n") {
+            self.out.push('\n');
+        }
+    }
+
+    fn has_comment_before(&self, pos: usize) -> bool {
+        self.comments
+            .get(self.next_comment)
+            .is_some_and(|comment| comment.start < pos)
+    }
+
+    /// Prints the comments before `pos` on lines of their own.
+    fn comments_before(&mut self, pos: usize, indent: usize) {
+        while self.has_comment_before(pos) {
+            let comment = self.comments[self.next_comment];
+            self.next_comment += 1;
+            if self.empty_line_before(comment.start) {
+                self.empty_line();
+            }
+            self.indent(indent);
+            if let Some(text) = self.source_of(comment) {
+                self.out.push_str(text.trim_end());
+            }
+            self.out.push('\n');
+            self.last_end = comment.end;
+        }
+    }
+
+    /// Prints the comments before `pos` in the middle of a line. Each of them
+    /// ends the line, which continues indented below it.
+    fn inline_comments_before(&mut self, pos: usize) {
+        while self.has_comment_before(pos) {
+            let comment = self.comments[self.next_comment];
+            self.next_comment += 1;
+            let len = self.out.trim_end_matches(' ').len();
+            self.out.truncate(len);
+            if self.out.is_empty() || self.out.ends_with('\n') {
+                self.indent(self.line_indent + 1);
+            } else {
+                self.out.push(' ');
+            }
+            if let Some(text) = self.source_of(comment) {
+                self.out.push_str(text.trim_end());
+            }
+            self.out.push('\n');
+            self.indent(self.line_indent + 1);
+            self.last_end = comment.end;
+        }
+    }
+
+    /// Start of the first token of the source at or after `pos`, skipping
+    /// whitespace and comments.
+    fn token_start(&self, mut pos: usize) -> usize {
+        let source = match self.source {
+            Some(source) => source,
+            None => return pos,
+        };
+        loop {
+            let rest = source.get(pos..).unwrap_or_default();
+            pos += rest.len() - rest.trim_start().len();
+            match self
+                .comments
+                .binary_search_by_key(&pos, |comment| comment.start)
+            {
+                Ok(i) => pos = self.comments[i].end,
+                Err(_) => return pos,
+            }
+        }
+    }
+
+    /// Prints `text`, the token that follows the last printed node, after
+    /// the comments before it in the source.
+    fn token(&mut self, text: &str) {
+        let start = self.token_start(self.pos);
+        self.inline_comments_before(start);
+        if self.out.trim_end_matches(' ').ends_with('\n') {
+            self.out.push_str(text.trim_start());
+        } else {
+            self.out.push_str(text);
+        }
+    }
+
+    /// Prints the comment right after `end` if only spaces are between them.
+    fn trailing_comment(&mut self, end: usize) {
+        let comment = match self.comments.get(self.next_comment) {
+            Some(comment) => *comment,
+            None => return,
+        };
+        let text = match self.source_of(comment) {
+            Some(text) => text,
+            None => return,
+        };
+        let same_line = self
+            .source
+            .and_then(|source| source.get(end..comment.start))
+            .is_some_and(|between| between.chars().all(|c| c != '\n' && c.is_whitespace()));
+        if same_line {
+            self.next_comment += 1;
+            self.out.push(' ');
+            self.out.push_str(text.trim_end());
+            self.last_end = comment.end;
+        }
+    }
+
+    /// Prints `stmt` on a new line, together with the comments around it.
+    fn line<S: AsRef<str>>(&mut self, stmt: &Statement<S>, indent: usize) {
+        self.comments_before(stmt.span.start, indent);
+        if self.empty_line_before(stmt.span.start) {
+            self.empty_line();
+        }
+        // Empty lines in the statement are looked for from its start.
+        self.last_end = stmt.span.start;
+        self.pos = stmt.span.start;
+        self.indent(indent);
+        let outer_indent = std::mem::replace(&mut self.line_indent, indent);
+        self.statement(stmt, indent);
+        self.line_indent = outer_indent;
+        self.pos = stmt.span.end;
+        self.last_end = stmt.span.end;
+        self.trailing_comment(stmt.span.end);
+    }
+
+    /// Prints each statement on its own line, function definitions are
+    /// separated from their neighbours by an empty line.
+    fn statements<S: AsRef<str>>(&mut self, stmts: &[Statement<S>], indent: usize) {
+        let is_function =
+            |stmt: &Statement<S>| matches!(stmt.kind, StatementKind::FunctionDef { .. });
+        for (i, stmt) in stmts.iter().enumerate() {
+            if i > 0 && (is_function(stmt) || is_function(&stmts[i - 1])) {
+                self.empty_line();
+            }
+            self.line(stmt, indent);
+            self.out.push('\n');
+        }
+    }
+
+    /// Prints the body of a compound statement. Blocks stay on the line of
+    /// the statement, anything else goes on the next line unless `braces`
+    /// asks to wrap it into a block. Returns whether the body ends with a
+    /// `}`.
+    fn body<S: AsRef<str>>(&mut self, body: &Statement<S>, indent: usize, braces: bool) -> bool {
+        match &body.kind {
+            StatementKind::Block { .. } => {
+                self.out.push(' ');
+                self.statement(body, indent);
+                true
+            }
+            _ if braces => {
+                self.out.push_str(" {\n");
+                self.line(body, indent + 1);
+                self.out.push('\n');
+                self.indent(indent);
+                self.out.push('}');
+                true
+            }
+            _ => {
+                self.out.push('\n');
+                // No empty line between the statement and its body.
+                self.last_end = self.last_end.max(body.span.start);
+                self.line(body, indent + 1);
+                false
+            }
+        }
+    }
+
+    /// Continues after a body with `keyword`, on the line of the `}` of a
+    /// block or on a new line. Comments before the keyword go on the lines
+    /// before it.
+    fn after_body(&mut self, braced: bool, keyword: &str, indent: usize) {
+        let start = self.token_start(self.pos);
+        if braced && !self.has_comment_before(start) {
+            self.out.push(' ');
+        } else {
+            self.last_end = self.last_end.max(self.pos);
+            if braced {
+                self.trailing_comment(self.pos);
+            }
+            self.out.push('\n');
+            self.comments_before(start, indent);
+            self.indent(indent);
+        }
+        self.out.push_str(keyword);
+    }
+
+    fn statement<S: AsRef<str>>(&mut self, stmt: &Statement<S>, indent: usize) {
+        match &stmt.kind {
+            StatementKind::If {
+                cond,
+                if_block,
+                else_block,
+            } => {
+                self.out.push_str("if (");
+                self.expression(cond);
+                self.token(")");
+                let else_block = match else_block {
+                    Some(else_block) => else_block,
+                    None => {
+                        self.body(if_block, indent, false);
+                        return;
+                    }
+                };
+                let braced = self.body(if_block, indent, if_block.ends_with_open_if());
+                self.after_body(braced, "else", indent);
+                if let StatementKind::If { .. } = else_block.kind {
+                    self.inline_comments_before(else_block.span.start);
+                    self.token(" ");
+                    self.statement(else_block, indent);
+                } else {
+                    self.body(else_block, indent, false);
+                }
+            }
+            StatementKind::While { cond, block } => {
+                self.out.push_str("while (");
+                self.expression(cond);
+                self.token(")");
+                self.body(block, indent, false);
+            }
+            StatementKind::For {
+                init_expr,
+                cond,
+                end_expr,
+                block,
+            } => {
+                self.out.push_str("for (");
+                if let Some(init_expr) = init_expr {
+                    self.expression(init_expr);
+                }
+                self.token(";");
+                if let Some(cond) = cond {
+                    self.out.push(' ');
+                    self.expression(cond);
+                }
+                self.token(";");
+                if let Some(end_expr) = end_expr {
+                    self.out.push(' ');
+                    self.expression(end_expr);
+                }
+                self.token(")");
+                self.body(block, indent, false);
+            }
+            StatementKind::Foreach { var, array, block } => {
+                self.out.push_str("foreach ");
+                self.ident(var);
+                self.token(" (");
+                self.expression(array);
+                self.token(")");
+                self.body(block, indent, false);
+            }
+            StatementKind::Repeat { block, cond } => {
+                self.out.push_str("repeat");
+                let braced = self.body(block, indent, false);
+                self.after_body(braced, "until ", indent);
+                self.expression(cond);
+                self.token(";");
+            }
+            StatementKind::FunctionDef {
+                ident,
+                params,
+                body,
+                ..
+            } => {
+                self.out.push_str("function ");
+                self.ident(ident);
+                self.token("(");
+                for (i, param) in params.iter().enumerate() {
+                    if i > 0 {
+                        self.token(", ");
+                    }
+                    self.ident(param);
+                }
+                self.token(")");
+                self.body(body, indent, true);
+            }
+            StatementKind::Declaration { scope, vars } => {
+                self.out.push_str(match scope {
+                    VarScope::Local => "local_var ",
+                    VarScope::Global => "global_var ",
+                });
+                for (i, var) in vars.iter().enumerate() {
+                    if i > 0 {
+                        self.token(", ");
+                    }
+                    self.ident(&var.ident);
+                    if let Some(init) = &var.init {
+                        self.token(" = ");
+                        self.operand(init, precedence(init) > Precedence::Comma);
+                    }
+                }
+                self.token(";");
+            }
+            StatementKind::Include { path, path_span } => {
+                self.out.push_str("include(");
+                self.inline_comments_before(path_span.start);
+                match self.source_of(*path_span) {
+                    Some(quoted) => self.out.push_str(quoted),
+                    None => write!(self.out, "\"{}\"", path.as_ref()).unwrap(),
+                }
+                self.pos = path_span.end;
+                self.token(");");
+            }
+            StatementKind::Return { expr } => {
+                self.out.push_str("return");
+                if let Some(expr) = expr {
+                    self.out.push(' ');
+                    self.expression(expr);
+                }
+                self.token(";");
+            }
+            StatementKind::Break => self.keyword("break", stmt.span.start),
+            StatementKind::Continue => self.keyword("continue", stmt.span.start),
+            StatementKind::Empty => self.out.push(';'),
+            StatementKind::Block { statements } => {
+                self.out.push('{');
+                if !statements.is_empty() || self.has_comment_before(stmt.span.end) {
+                    self.out.push('\n');
+                    self.statements(statements, indent + 1);
+                    self.comments_before(stmt.span.end, indent + 1);
+                    self.indent(indent);
+                }
+                self.out.push('}');
+                self.pos = stmt.span.end;
+            }
+            StatementKind::Expression(expr) => {
+                self.expression(expr);
+                self.token(";");
+            }
+            StatementKind::Repeated { call, count } => {
+                self.expression(call);
+                self.token(" x ");
+                self.expression(count);
+                self.token(";");
+            }
+        }
+    }
+
+    /// Prints `expr`, in parentheses unless it `fits` where it is used.
+    fn operand<S: AsRef<str>>(&mut self, expr: &Expression<S>, fits: bool) {
+        if fits {
+            self.expression(expr);
+        } else {
+            self.out.push('(');
+            self.expression(expr);
+            self.out.push(')');
+        }
+    }
+
+    /// Prints a name of the source.
+    fn ident<S: AsRef<str>>(&mut self, ident: &Ident<S>) {
+        self.inline_comments_before(ident.span.start);
+        self.out.push_str(ident.name.as_ref());
+        self.pos = ident.span.end;
+    }
+
+    /// Prints a statement that is only the keyword at `start`.
+    fn keyword(&mut self, keyword: &str, start: usize) {
+        self.out.push_str(keyword);
+        self.pos = start + keyword.len();
+        self.token(";");
+    }
+
+    fn expression<S: AsRef<str>>(&mut self, expr: &Expression<S>) {
+        self.inline_comments_before(expr.span.start);
+        self.expression_kind(expr);
+        self.pos = expr.span.end;
+    }
+
+    fn expression_kind<S: AsRef<str>>(&mut self, expr: &Expression<S>) {
+        match &expr.kind {
+            ExpressionKind::Identifier(name) => self.out.push_str(name.as_ref()),
+            ExpressionKind::Number(value) => match self.source_of(expr.span) {
+                Some(literal) => self.out.push_str(literal),
+                None => write!(self.out, "{}", value).unwrap(),
+            },
+            ExpressionKind::String(value) => write!(self.out, "\"{}\"", value.as_ref()).unwrap(),
+            ExpressionKind::Data(value) => write!(self.out, "'{}'", value.as_ref()).unwrap(),
+            ExpressionKind::Boolean(true) => self.out.push_str("TRUE"),
+            ExpressionKind::Boolean(false) => self.out.push_str("FALSE"),
+            ExpressionKind::Null => self.out.push_str("NULL"),
+            ExpressionKind::Prefix { op, expr } => {
+                self.out.push_str(op.as_str());
+                let start = self.out.len();
+                self.operand(expr, precedence(expr) >= Precedence::Prefix);
+                // `- -x` must not become `--x`, nor `! ~x` become `!~x`.
+                let next = self.out[start..].chars().next();
+                let glued = match op {
+                    UnaryOp::Plus | UnaryOp::Incr => next == Some('+'),
+                    UnaryOp::Minus | UnaryOp::Decr => next == Some('-'),
+                    UnaryOp::Not => next == Some('~'),
+                    UnaryOp::BitNot => false,
+                };
+                if glued {
+                    self.out.insert(start, ' ');
+                }
+            }
+            ExpressionKind::Postfix { expr, op } => {
+                self.operand(expr, precedence(expr) >= Precedence::Postfix);
+                self.token(op.as_str());
+            }
+            ExpressionKind::Infix { l_expr, op, r_expr } => {
+                let prec = Precedence::of_binary(*op);
+                // `**` is right associative and binds its right operand like
+                // a prefix operator, all other operators are left
+                // associative.
+                let (l_fits, r_fits) = match op {
+                    BinaryOp::Pow => (
+                        precedence(l_expr) > prec,
+                        precedence(r_expr) >= Precedence::Prefix,
+                    ),
+                    _ => (precedence(l_expr) >= prec, precedence(r_expr) > prec),
+                };
+                self.operand(l_expr, l_fits);
+                self.token(&format!(" {} ", op));
+                self.operand(r_expr, r_fits);
+            }
+            ExpressionKind::Assign { target, op, expr } => {
+                self.operand(target, precedence(target) >= Precedence::Postfix);
+                self.token(&format!(" {} ", op));
+                self.operand(expr, precedence(expr) > Precedence::Comma);
+            }
+            ExpressionKind::Index { expr, index } => {
+                self.operand(expr, precedence(expr) >= Precedence::Postfix);
+                self.token("[");
+                self.expression(index);
+                self.token("]");
+            }
+            ExpressionKind::Grouped(expr) => {
+                self.out.push('(');
+                self.expression(expr);
+                self.token(")");
+            }
+            ExpressionKind::Sequence(exprs) => {
+                for (i, expr) in exprs.iter().enumerate() {
+                    if i > 0 {
+                        self.token(", ");
+                    }
+                    self.operand(expr, precedence(expr) > Precedence::Comma);
+                }
+            }
+            ExpressionKind::FunctionRef(ident) => {
+                self.out.push('@');
+                self.ident(ident);
+            }
+            ExpressionKind::FunctionCall { ident, args } => {
+                self.ident(ident);
+                self.token("(");
+                for (i, arg) in args.iter().enumerate() {
+                    if i > 0 {
+                        self.token(", ");
+                    }
+                    if let Arg::Named(name, _) = arg {
+                        self.ident(name);
+                        self.token(":");
+                    }
+                    let value = arg.value();
+                    self.operand(value, precedence(value) > Precedence::Comma);
+                }
+                self.token(")");
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::parser::Parser;
+    use crate::visit::{walk_expression_mut, walk_statements_mut, VisitorMut};
+
+    fn nasl(code: &str) -> String {
+        to_nasl(&Parser::new(code).parse().unwrap())
+    }
+
+    /// Prints `code` without the parentheses of the source, so only the ones
+    /// required by the precedence are left.
+    fn minimal(code: &str) -> String {
+        struct Ungroup;
+
+        impl<'a> VisitorMut<&'a str> for Ungroup {
+            fn visit_expression_mut(&mut self, expr: &mut Expression<&'a str>) {
+                while let ExpressionKind::Grouped(inner) = &mut expr.kind {
+                    let inner = std::mem::replace(
+                        &mut **inner,
+                        Expression::new(ExpressionKind::Null, expr.span),
+                    );
+                    *expr = inner;
+                }
+                walk_expression_mut(self, expr);
+            }
+        }
+
+        let mut stmts = Parser::new(code).parse().unwrap();
+        walk_statements_mut(&mut Ungroup, &mut stmts);
+        to_nasl(&stmts)
+    }
+
+    #[test]
+    fn parentheses() {
+        let cases = [
+            ("(a + b) * c;", "(a + b) * c;\n"),
+            ("a + (b * c);", "a + b * c;\n"),
+            ("(a - b) - c;", "a - b - c;\n"),
+            ("a - (b - c);", "a - (b - c);\n"),
+            ("(2 ** 3) ** 4;", "(2 ** 3) ** 4;\n"),
+            ("2 ** (3 ** 4);", "2 ** 3 ** 4;\n"),
+            ("2 ** (-x);", "2 ** -x;\n"),
+            ("-(x ** 2);", "-x ** 2;\n"),
+            ("(-x) ** 2;", "(-x) ** 2;\n"),
+            ("-(a + b);", "-(a + b);\n"),
+            ("(a || b) && !(c || d);", "(a || b) && !(c || d);\n"),
+            ("a = (b = c);", "a = b = c;\n"),
+            ("a + (b = 1);", "a + (b = 1);\n"),
+            ("(x[1])++;", "x[1]++;\n"),
+            ("f((a = 1), port:(p + 1));", "f(a = 1, port:p + 1);\n"),
+            ("local_var a = (b == c);", "local_var a = b == c;\n"),
+        ];
+        for (code, expected) in &cases {
+            assert_eq!(minimal(code), *expected, "{}", code);
+        }
+    }
+
+    #[test]
+    fn adjacent_operators() {
+        assert_eq!(nasl("- -x;"), "- -x;\n");
+        assert_eq!(nasl("-(--x);"), "-(--x);\n");
+        assert_eq!(minimal("-(--x);"), "- --x;\n");
+        assert_eq!(minimal("+(+x);"), "+ +x;\n");
+        assert_eq!(minimal("!(~x);"), "! ~x;\n");
+        assert_eq!(minimal("!(!x);"), "!!x;\n");
+        assert_eq!(minimal("a - (-b);"), "a - -b;\n");
+    }
+
+    #[test]
+    fn statements() {
+        let code = "include('a.inc'); function f(a,b){local_var c=0x10,d;\
+            for(i=0,j=1;i<a;i++){if(i)continue;else break;}\
+            for(;;)exit(0);while(b)b--;foreach x(l){}repeat c++;until c>3;\
+            f(1,b:2) x 3;return c;} if(a){}else if(b)c();else{d();}";
+        let expected = "include(\"a.inc\");
+
+function f(a, b) {
+  local_var c = 16, d;
+  for (i = 0, j = 1; i < a; i++) {
+    if (i)
+      continue;
+    else
+      break;
+  }
+  for (;;)
+    exit(0);
+  while (b)
+    b--;
+  foreach x (l) {}
+  repeat
+    c++;
+  until c > 3;
+  f(1, b:2) x 3;
+  return c;
+}
+
+if (a) {} else if (b)
+  c();
+else {
+  d();
+}
+";
+        assert_eq!(nasl(code), expected);
+    }
+
+    #[test]
+    fn dangling_else() {
+        // `if (a) if (b) c; else d;` can only be parsed with the `else` on
+        // the inner `if`, so a tree with the `else` on the outer one needs
+        // braces.
+        let mut stmts = Parser::new("if (a) if (b) c(); else d();").parse().unwrap();
+        if let StatementKind::If {
+            if_block,
+            else_block,
+            ..
+        } = &mut stmts[0].kind
+        {
+            if let StatementKind::If {
+                else_block: inner, ..
+            } = &mut if_block.kind
+            {
+                *else_block = inner.take();
+            }
+        }
+        assert_eq!(
+            to_nasl(&stmts),
+            "if (a) {\n  if (b)\n    c();\n} else\n  d();\n"
+        );
+    }
+}
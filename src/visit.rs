@@ -0,0 +1,428 @@
+//! Traversal of the AST.
+//!
+//! A `Visitor` overrides the `visit_*` methods for the nodes it is
+//! interested in. The default methods call the matching `walk_*` function,
+//! which visits the children of the node, so an overriding method calls it
+//! as well unless it wants to skip the children:
+//!
+//! ```
+//! use nasl_transpiler::ast::{Expression, ExpressionKind};
+//! use nasl_transpiler::visit::{walk_expression, Visitor};
+//!
+//! #[derive(Default)]
+//! struct Calls<'a>(Vec<&'a str>);
+//!
+//! impl<'ast, 'a> Visitor<'ast, &'a str> for Calls<'a> {
+//!     fn visit_expression(&mut self, expr: &'ast Expression<&'a str>) {
+//!         if let ExpressionKind::FunctionCall { ident, .. } = &expr.kind {
+//!             self.0.push(ident.name);
+//!         }
+//!         walk_expression(self, expr);
+//!     }
+//! }
+//! ```
+//!
+//! `VisitorMut` does the same for changing the tree in place.
+
+use crate::ast::*;
+
+pub trait Visitor<'ast, S> {
+    fn visit_statement(&mut self, stmt: &'ast Statement<S>) {
+        walk_statement(self, stmt);
+    }
+
+    fn visit_expression(&mut self, expr: &'ast Expression<S>) {
+        walk_expression(self, expr);
+    }
+
+    fn visit_var_decl(&mut self, var: &'ast VarDecl<S>) {
+        walk_var_decl(self, var);
+    }
+
+    /// Called for the names of defined functions, function parameters,
+    /// `foreach` variables, function references, the names of named
+    /// arguments and declared variables.
+    fn visit_ident(&mut self, _ident: &'ast Ident<S>) {}
+}
+
+pub fn walk_statements<'ast, S, V>(visitor: &mut V, stmts: &'ast [Statement<S>])
+where
+    V: Visitor<'ast, S> + ?Sized,
+{
+    for stmt in stmts {
+        visitor.visit_statement(stmt);
+    }
+}
+
+pub fn walk_statement<'ast, S, V>(visitor: &mut V, stmt: &'ast Statement<S>)
+where
+    V: Visitor<'ast, S> + ?Sized,
+{
+    match &stmt.kind {
+        StatementKind::If {
+            cond,
+            if_block,
+            else_block,
+        } => {
+            visitor.visit_expression(cond);
+            visitor.visit_statement(if_block);
+            if let Some(else_block) = else_block {
+                visitor.visit_statement(else_block);
+            }
+        }
+        StatementKind::While { cond, block } => {
+            visitor.visit_expression(cond);
+            visitor.visit_statement(block);
+        }
+        StatementKind::For {
+            init_expr,
+            cond,
+            end_expr,
+            block,
+        } => {
+            for expr in init_expr.iter().chain(cond).chain(end_expr) {
+                visitor.visit_expression(expr);
+            }
+            visitor.visit_statement(block);
+        }
+        StatementKind::Foreach { var, array, block } => {
+            visitor.visit_ident(var);
+            visitor.visit_expression(array);
+            visitor.visit_statement(block);
+        }
+        StatementKind::Repeat { block, cond } => {
+            visitor.visit_statement(block);
+            visitor.visit_expression(cond);
+        }
+        StatementKind::FunctionDef {
+            ident,
+            params,
+            body,
+            ..
+        } => {
+            visitor.visit_ident(ident);
+            for param in params {
+                visitor.visit_ident(param);
+            }
+            visitor.visit_statement(body);
+        }
+        StatementKind::Declaration { vars, .. } => {
+            for var in vars {
+                visitor.visit_var_decl(var);
+            }
+        }
+        StatementKind::Return { expr } => {
+            if let Some(expr) = expr {
+                visitor.visit_expression(expr);
+            }
+        }
+        StatementKind::Include { .. }
+        | StatementKind::Break
+        | StatementKind::Continue
+        | StatementKind::Empty => {}
+        StatementKind::Block { statements } => walk_statements(visitor, statements),
+        StatementKind::Expression(expr) => visitor.visit_expression(expr),
+        StatementKind::Repeated { call, count } => {
+            visitor.visit_expression(call);
+            visitor.visit_expression(count);
+        }
+    }
+}
+
+pub fn walk_expression<'ast, S, V>(visitor: &mut V, expr: &'ast Expression<S>)
+where
+    V: Visitor<'ast, S> + ?Sized,
+{
+    match &expr.kind {
+        ExpressionKind::Identifier(_)
+        | ExpressionKind::Number(_)
+        | ExpressionKind::String(_)
+        | ExpressionKind::Data(_)
+        | ExpressionKind::Boolean(_)
+        | ExpressionKind::Null => {}
+        ExpressionKind::Prefix { expr, .. }
+        | ExpressionKind::Postfix { expr, .. }
+        | ExpressionKind::Grouped(expr) => visitor.visit_expression(expr),
+        ExpressionKind::Infix { l_expr, r_expr, .. } => {
+            visitor.visit_expression(l_expr);
+            visitor.visit_expression(r_expr);
+        }
+        ExpressionKind::Index { expr, index } => {
+            visitor.visit_expression(expr);
+            visitor.visit_expression(index);
+        }
+        ExpressionKind::Sequence(exprs) => {
+            for expr in exprs {
+                visitor.visit_expression(expr);
+            }
+        }
+        ExpressionKind::FunctionRef(ident) => visitor.visit_ident(ident),
+        ExpressionKind::FunctionCall { ident, args } => {
+            visitor.visit_ident(ident);
+            for arg in args {
+                match arg {
+                    Arg::Anon(value) => visitor.visit_expression(value),
+                    Arg::Named(name, value) => {
+                        visitor.visit_ident(name);
+                        visitor.visit_expression(value);
+                    }
+                }
+            }
+        }
+        ExpressionKind::Assign { target, expr, .. } => {
+            visitor.visit_expression(target);
+            visitor.visit_expression(expr);
+        }
+    }
+}
+
+pub fn walk_var_decl<'ast, S, V>(visitor: &mut V, var: &'ast VarDecl<S>)
+where
+    V: Visitor<'ast, S> + ?Sized,
+{
+    visitor.visit_ident(&var.ident);
+    if let Some(init) = &var.init {
+        visitor.visit_expression(init);
+    }
+}
+
+pub trait VisitorMut<S> {
+    fn visit_statement_mut(&mut self, stmt: &mut Statement<S>) {
+        walk_statement_mut(self, stmt);
+    }
+
+    fn visit_expression_mut(&mut self, expr: &mut Expression<S>) {
+        walk_expression_mut(self, expr);
+    }
+
+    fn visit_var_decl_mut(&mut self, var: &mut VarDecl<S>) {
+        walk_var_decl_mut(self, var);
+    }
+
+    /// Called for the names of defined functions, function parameters,
+    /// `foreach` variables, function references, the names of named
+    /// arguments and declared variables.
+    fn visit_ident_mut(&mut self, _ident: &mut Ident<S>) {}
+}
+
+pub fn walk_statements_mut<S, V>(visitor: &mut V, stmts: &mut [Statement<S>])
+where
+    V: VisitorMut<S> + ?Sized,
+{
+    for stmt in stmts {
+        visitor.visit_statement_mut(stmt);
+    }
+}
+
+pub fn walk_statement_mut<S, V>(visitor: &mut V, stmt: &mut Statement<S>)
+where
+    V: VisitorMut<S> + ?Sized,
+{
+    match &mut stmt.kind {
+        StatementKind::If {
+            cond,
+            if_block,
+            else_block,
+        } => {
+            visitor.visit_expression_mut(cond);
+            visitor.visit_statement_mut(if_block);
+            if let Some(else_block) = else_block {
+                visitor.visit_statement_mut(else_block);
+            }
+        }
+        StatementKind::While { cond, block } => {
+            visitor.visit_expression_mut(cond);
+            visitor.visit_statement_mut(block);
+        }
+        StatementKind::For {
+            init_expr,
+            cond,
+            end_expr,
+            block,
+        } => {
+            for expr in init_expr.iter_mut().chain(cond).chain(end_expr) {
+                visitor.visit_expression_mut(expr);
+            }
+            visitor.visit_statement_mut(block);
+        }
+        StatementKind::Foreach { var, array, block } => {
+            visitor.visit_ident_mut(var);
+            visitor.visit_expression_mut(array);
+            visitor.visit_statement_mut(block);
+        }
+        StatementKind::Repeat { block, cond } => {
+            visitor.visit_statement_mut(block);
+            visitor.visit_expression_mut(cond);
+        }
+        StatementKind::FunctionDef {
+            ident,
+            params,
+            body,
+            ..
+        } => {
+            visitor.visit_ident_mut(ident);
+            for param in params {
+                visitor.visit_ident_mut(param);
+            }
+            visitor.visit_statement_mut(body);
+        }
+        StatementKind::Declaration { vars, .. } => {
+            for var in vars {
+                visitor.visit_var_decl_mut(var);
+            }
+        }
+        StatementKind::Return { expr } => {
+            if let Some(expr) = expr {
+                visitor.visit_expression_mut(expr);
+            }
+        }
+        StatementKind::Include { .. }
+        | StatementKind::Break
+        | StatementKind::Continue
+        | StatementKind::Empty => {}
+        StatementKind::Block { statements } => walk_statements_mut(visitor, statements),
+        StatementKind::Expression(expr) => visitor.visit_expression_mut(expr),
+        StatementKind::Repeated { call, count } => {
+            visitor.visit_expression_mut(call);
+            visitor.visit_expression_mut(count);
+        }
+    }
+}
+
+pub fn walk_expression_mut<S, V>(visitor: &mut V, expr: &mut Expression<S>)
+where
+    V: VisitorMut<S> + ?Sized,
+{
+    match &mut expr.kind {
+        ExpressionKind::Identifier(_)
+        | ExpressionKind::Number(_)
+        | ExpressionKind::String(_)
+        | ExpressionKind::Data(_)
+        | ExpressionKind::Boolean(_)
+        | ExpressionKind::Null => {}
+        ExpressionKind::Prefix { expr, .. }
+        | ExpressionKind::Postfix { expr, .. }
+        | ExpressionKind::Grouped(expr) => visitor.visit_expression_mut(expr),
+        ExpressionKind::Infix { l_expr, r_expr, .. } => {
+            visitor.visit_expression_mut(l_expr);
+            visitor.visit_expression_mut(r_expr);
+        }
+        ExpressionKind::Index { expr, index } => {
+            visitor.visit_expression_mut(expr);
+            visitor.visit_expression_mut(index);
+        }
+        ExpressionKind::Sequence(exprs) => {
+            for expr in exprs {
+                visitor.visit_expression_mut(expr);
+            }
+        }
+        ExpressionKind::FunctionRef(ident) => visitor.visit_ident_mut(ident),
+        ExpressionKind::FunctionCall { ident, args } => {
+            visitor.visit_ident_mut(ident);
+            for arg in args {
+                match arg {
+                    Arg::Anon(value) => visitor.visit_expression_mut(value),
+                    Arg::Named(name, value) => {
+                        visitor.visit_ident_mut(name);
+                        visitor.visit_expression_mut(value);
+                    }
+                }
+            }
+        }
+        ExpressionKind::Assign { target, expr, .. } => {
+            visitor.visit_expression_mut(target);
+            visitor.visit_expression_mut(expr);
+        }
+    }
+}
+
+pub fn walk_var_decl_mut<S, V>(visitor: &mut V, var: &mut VarDecl<S>)
+where
+    V: VisitorMut<S> + ?Sized,
+{
+    visitor.visit_ident_mut(&mut var.ident);
+    if let Some(init) = &mut var.init {
+        visitor.visit_expression_mut(init);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::parser::Parser;
+
+    const CODE: &str = r#"
+        function f(a, b) { local_var c = a; return @g; }
+        for (i = 0, j = 1; i < 3; i++) { if (x) f(a:i, b:-j); else break; }
+        repeat { foreach v (l) y[v] = (v); } until (z);
+        send(x) x 2;
+    "#;
+
+    #[derive(Default)]
+    struct Counter {
+        statements: usize,
+        expressions: usize,
+        idents: Vec<String>,
+    }
+
+    impl<'ast, 'a> Visitor<'ast, &'a str> for Counter {
+        fn visit_statement(&mut self, stmt: &'ast Statement<&'a str>) {
+            self.statements += 1;
+            walk_statement(self, stmt);
+        }
+
+        fn visit_expression(&mut self, expr: &'ast Expression<&'a str>) {
+            self.expressions += 1;
+            walk_expression(self, expr);
+        }
+
+        fn visit_ident(&mut self, ident: &'ast Ident<&'a str>) {
+            self.idents.push(ident.name.to_string());
+        }
+    }
+
+    #[test]
+    fn visits_every_node() {
+        let stmts = Parser::new(CODE).parse().unwrap();
+        let mut counter = Counter::default();
+        walk_statements(&mut counter, &stmts);
+        assert_eq!(counter.statements, 14);
+        assert_eq!(counter.expressions, 31);
+        assert_eq!(
+            counter.idents,
+            vec!["f", "a", "b", "c", "g", "f", "a", "b", "v", "send"]
+        );
+    }
+
+    struct Rename;
+
+    impl<'a> VisitorMut<&'a str> for Rename {
+        fn visit_expression_mut(&mut self, expr: &mut Expression<&'a str>) {
+            if let ExpressionKind::Identifier(name) = &mut expr.kind {
+                if *name == "i" {
+                    *name = "k";
+                }
+            }
+            walk_expression_mut(self, expr);
+        }
+
+        fn visit_ident_mut(&mut self, ident: &mut Ident<&'a str>) {
+            if ident.name == "a" {
+                ident.name = "k";
+            }
+        }
+    }
+
+    #[test]
+    fn changes_in_place() {
+        let mut stmts = Parser::new(CODE).parse().unwrap();
+        walk_statements_mut(&mut Rename, &mut stmts);
+        // The new names have the same length, so the spans do not change.
+        let renamed = CODE
+            .replace("f(a, b)", "f(k, b)")
+            .replace("i = 0", "k = 0")
+            .replace("i < 3; i++", "k < 3; k++")
+            .replace("a:i", "k:k");
+        assert_eq!(stmts, Parser::new(&renamed).parse().unwrap());
+    }
+}
@@ -0,0 +1,178 @@
+//! Tests of the include graph and the `deps` command line mode.
+
+use nasl_transpiler::graph::{IncludeGraph, NodeId};
+use nasl_transpiler::include::IncludeResolver;
+use std::env;
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::process::{Command, Output};
+
+/// Writes a feed with an extra include directory below `name`, returns
+/// both.
+fn feed(name: &str) -> (PathBuf, PathBuf) {
+    let dir = Path::new(env!("CARGO_TARGET_TMPDIR")).join(name);
+    let _ = fs::remove_dir_all(&dir);
+    let files = [
+        (
+            "feed/plugin.nasl",
+            "include('a.inc');\ninclude('missing.inc');\n",
+        ),
+        ("feed/sub/other.nasl", "include('b.inc');\n"),
+        ("feed/a.inc", "include('b.inc');\ninclude('ext.inc');\n"),
+        ("feed/b.inc", "function b() {}\n"),
+        ("feed/unused.inc", "include('b.inc');\n"),
+        ("ext/ext.inc", "function ext() {}\n"),
+    ];
+    for (name, code) in files.iter() {
+        let path = dir.join(name);
+        fs::create_dir_all(path.parent().unwrap()).unwrap();
+        fs::write(path, code).unwrap();
+    }
+    (dir.join("feed"), dir.join("ext"))
+}
+
+fn deps(args: &[&str]) -> Output {
+    Command::new(env!("CARGO_BIN_EXE_nasl-transpiler"))
+        .arg("deps")
+        .args(args)
+        .output()
+        .unwrap()
+}
+
+#[test]
+fn graph() {
+    let (feed, ext) = feed("deps_graph");
+    let mut includes = IncludeResolver::new();
+    includes.add_search_path(&feed);
+    includes.add_search_path(&ext);
+    let (graph, errors) = IncludeGraph::build(&feed, &mut includes).unwrap();
+
+    let names = |ids: Vec<NodeId>| -> Vec<String> {
+        ids.into_iter()
+            .map(|id| graph.node(id).name.clone())
+            .collect()
+    };
+    let all: Vec<_> = graph
+        .nodes()
+        .iter()
+        .map(|node| node.name.as_str())
+        .collect();
+    assert_eq!(
+        all,
+        [
+            "a.inc",
+            "b.inc",
+            "plugin.nasl",
+            "sub/other.nasl",
+            "unused.inc",
+            "ext.inc"
+        ]
+    );
+    // Files of the feed that are included are parsed once.
+    assert_eq!(includes.files().len(), 4);
+
+    let plugin = graph.find("plugin.nasl").unwrap();
+    assert_eq!(names(graph.node(plugin).includes.clone()), ["a.inc"]);
+    assert_eq!(names(graph.closure(plugin)), ["a.inc", "b.inc", "ext.inc"]);
+    let b = graph.find("b.inc").unwrap();
+    assert_eq!(
+        names(graph.dependents(b)),
+        ["a.inc", "plugin.nasl", "sub/other.nasl", "unused.inc"]
+    );
+    assert_eq!(names(graph.unused()), ["unused.inc"]);
+
+    assert_eq!(errors.len(), 1);
+    assert!(errors[0].path.as_ref().unwrap().ends_with("plugin.nasl"));
+    assert!(errors[0].message.contains("missing.inc"));
+}
+
+#[test]
+fn mixed_paths() {
+    let (feed, ext) = feed("deps_mixed_paths");
+    // The feed is walked as `feed` and searched as `./feed`, and `ext` is
+    // searched as `ext/../ext`. Each file has to be a single node anyway.
+    let cwd = env::current_dir().unwrap();
+    let feed = feed.strip_prefix(&cwd).unwrap_or(&feed).to_path_buf();
+    let mut includes = IncludeResolver::new();
+    includes.add_search_path(Path::new(".").join(&feed));
+    includes.add_search_path(ext.join("..").join("ext"));
+    let (graph, errors) = IncludeGraph::build(&feed, &mut includes).unwrap();
+
+    let all: Vec<_> = graph
+        .nodes()
+        .iter()
+        .map(|node| node.name.as_str())
+        .collect();
+    assert_eq!(
+        all,
+        [
+            "a.inc",
+            "b.inc",
+            "plugin.nasl",
+            "sub/other.nasl",
+            "unused.inc",
+            "ext.inc"
+        ]
+    );
+    assert_eq!(includes.files().len(), 4);
+    let b = graph.find("b.inc").unwrap();
+    assert_eq!(graph.dependents(b).len(), 4);
+    assert_eq!(errors.len(), 1);
+}
+
+#[test]
+fn cycles() {
+    let dir = Path::new(env!("CARGO_TARGET_TMPDIR")).join("deps_cycles");
+    let _ = fs::remove_dir_all(&dir);
+    fs::create_dir_all(&dir).unwrap();
+    fs::write(dir.join("x.inc"), "include('y.inc');").unwrap();
+    fs::write(dir.join("y.inc"), "include('x.inc');").unwrap();
+
+    let mut includes = IncludeResolver::new();
+    includes.add_search_path(&dir);
+    let (graph, _) = IncludeGraph::build(&dir, &mut includes).unwrap();
+    let x = graph.find("x.inc").unwrap();
+    let y = graph.find("y.inc").unwrap();
+    assert_eq!(graph.closure(x), [x, y]);
+    assert_eq!(graph.unused(), [x, y]);
+}
+
+#[test]
+fn command_line() {
+    let (feed, ext) = feed("deps_command_line");
+    let (feed, ext) = (feed.to_str().unwrap(), ext.to_str().unwrap());
+
+    let output = deps(&["--format", "json", "-I", ext, feed]);
+    assert_eq!(output.status.code(), Some(0));
+    assert!(String::from_utf8(output.stderr)
+        .unwrap()
+        .contains("missing.inc"));
+    let json: serde_json::Value = serde_json::from_slice(&output.stdout).unwrap();
+    assert_eq!(json["unused"], serde_json::json!(["unused.inc"]));
+    assert_eq!(
+        json["files"][2],
+        serde_json::json!({
+            "name": "plugin.nasl",
+            "includes": ["a.inc"],
+            "closure": ["a.inc", "b.inc", "ext.inc"],
+        })
+    );
+
+    let output = deps(&["-I", ext, feed]);
+    assert_eq!(output.status.code(), Some(0));
+    let dot = String::from_utf8(output.stdout).unwrap();
+    assert!(dot.starts_with("digraph includes {\n"), "{}", dot);
+    assert!(dot.contains("  \"plugin.nasl\" -> \"a.inc\";\n"), "{}", dot);
+    assert!(
+        dot.contains("  \"unused.inc\" [style=dashed];\n"),
+        "{}",
+        dot
+    );
+}
+
+#[test]
+fn usage() {
+    assert_eq!(deps(&[]).status.code(), Some(2));
+    assert_eq!(deps(&["--format", "svg", "x"]).status.code(), Some(2));
+    assert_eq!(deps(&["-I"]).status.code(), Some(2));
+}
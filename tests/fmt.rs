@@ -0,0 +1,77 @@
+//! Tests of the `fmt` command line mode.
+
+use std::fs;
+use std::path::Path;
+use std::process::{Command, Output};
+
+fn fmt(args: &[&str]) -> Output {
+    Command::new(env!("CARGO_BIN_EXE_nasl-transpiler"))
+        .arg("fmt")
+        .args(args)
+        .output()
+        .unwrap()
+}
+
+#[test]
+fn check_and_write() {
+    let dir = Path::new(env!("CARGO_TARGET_TMPDIR")).join("fmt");
+    let _ = fs::remove_dir_all(&dir);
+    fs::create_dir_all(dir.join("inc")).unwrap();
+    let formatted = dir.join("formatted.nasl");
+    let unformatted = dir.join("inc/unformatted.inc");
+    fs::write(&formatted, "# ok\nx = 1;\n").unwrap();
+    fs::write(&unformatted, "function f(){return 1;} # one\n").unwrap();
+    fs::write(dir.join("notes.txt"), "not nasl").unwrap();
+    let dir = dir.to_str().unwrap();
+
+    let output = fmt(&["--check", dir]);
+    assert_eq!(output.status.code(), Some(1));
+    let stdout = String::from_utf8(output.stdout).unwrap();
+    assert!(stdout.contains("unformatted.inc"), "{}", stdout);
+    assert!(!stdout.contains("formatted.nasl"), "{}", stdout);
+    assert_eq!(
+        fs::read_to_string(&unformatted).unwrap(),
+        "function f(){return 1;} # one\n"
+    );
+
+    assert_eq!(fmt(&[dir]).status.code(), Some(0));
+    assert_eq!(
+        fs::read_to_string(&unformatted).unwrap(),
+        "function f() {\n  return 1;\n} # one\n"
+    );
+    assert_eq!(fmt(&["--check", dir]).status.code(), Some(0));
+
+    let broken = Path::new(dir).join("broken.nasl");
+    fs::write(&broken, "if (").unwrap();
+    let output = fmt(&["--check", broken.to_str().unwrap()]);
+    assert_eq!(output.status.code(), Some(1));
+    assert!(String::from_utf8(output.stderr)
+        .unwrap()
+        .contains("broken.nasl"));
+}
+
+#[test]
+fn latin1() {
+    let dir = Path::new(env!("CARGO_TARGET_TMPDIR")).join("fmt_latin1");
+    let _ = fs::remove_dir_all(&dir);
+    fs::create_dir_all(&dir).unwrap();
+    let file = dir.join("latin1.nasl");
+    let code = b"x = 1;\ndisplay('M\xfcller');\n";
+    fs::write(&file, &code[..]).unwrap();
+
+    let output = fmt(&[file.to_str().unwrap()]);
+    assert_eq!(output.status.code(), Some(1));
+    let stderr = String::from_utf8(output.stderr).unwrap();
+    assert!(
+        stderr.contains("latin1.nasl: invalid UTF-8 at 2:11"),
+        "{}",
+        stderr
+    );
+    assert_eq!(fs::read(&file).unwrap(), &code[..]);
+}
+
+#[test]
+fn usage() {
+    assert_eq!(fmt(&[]).status.code(), Some(2));
+    assert_eq!(fmt(&["--frobnicate", "x"]).status.code(), Some(2));
+}
@@ -0,0 +1,53 @@
+//! Golden tests of the parser.
+//!
+//! Every `tests/golden/*.nasl` file is parsed and dumped as S-expression,
+//! which has to match the `.sexp` file next to it. Run the tests with
+//! `UPDATE_GOLDEN=1` to write the current output to the `.sexp` files and
+//! review the changes with `git diff`.
+
+use nasl_transpiler::parser::Parser;
+use nasl_transpiler::sexp::to_sexp;
+use std::fs;
+use std::path::Path;
+
+#[test]
+fn golden() {
+    let dir = Path::new(env!("CARGO_MANIFEST_DIR")).join("tests/golden");
+    let update = std::env::var_os("UPDATE_GOLDEN").is_some();
+    let mut failures = Vec::new();
+
+    let mut inputs: Vec<_> = fs::read_dir(&dir)
+        .unwrap()
+        .map(|entry| entry.unwrap().path())
+        .filter(|path| path.extension().is_some_and(|ext| ext == "nasl"))
+        .collect();
+    inputs.sort();
+    assert!(!inputs.is_empty(), "no inputs in {}", dir.display());
+
+    for input in inputs {
+        let code = fs::read_to_string(&input).unwrap();
+        let actual = match Parser::new(&code).parse() {
+            Ok(stmts) => to_sexp(&stmts),
+            Err(errors) => {
+                failures.push(format!("{}: {:?}", input.display(), errors));
+                continue;
+            }
+        };
+        let output = input.with_extension("sexp");
+        if update {
+            fs::write(&output, &actual).unwrap();
+            continue;
+        }
+        let expected = fs::read_to_string(&output).unwrap_or_default();
+        if actual != expected {
+            failures.push(format!(
+                "{} differs from {}:\n{}",
+                input.display(),
+                output.display(),
+                actual
+            ));
+        }
+    }
+
+    assert!(failures.is_empty(), "{}", failures.join("\n\n"));
+}
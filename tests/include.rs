@@ -0,0 +1,147 @@
+//! Tests of the resolution of included files.
+
+use nasl_transpiler::ast::VarScope;
+use nasl_transpiler::error::DiagnosticKind;
+use nasl_transpiler::include::IncludeResolver;
+use nasl_transpiler::parser::Parser;
+use nasl_transpiler::resolve::{resolve_with_includes, Binding, Builtins, DefKind};
+use nasl_transpiler::span::Span;
+use std::fs;
+use std::path::{Path, PathBuf};
+
+fn write_files(dir: &Path, files: &[(&str, &str)]) {
+    fs::create_dir_all(dir).unwrap();
+    for (name, code) in files {
+        fs::write(dir.join(name), code).unwrap();
+    }
+}
+
+fn feed(name: &str) -> PathBuf {
+    let dir = Path::new(env!("CARGO_TARGET_TMPDIR")).join(name);
+    let _ = fs::remove_dir_all(&dir);
+    dir
+}
+
+#[test]
+fn includes() {
+    let dir = feed("include");
+    write_files(
+        &dir.join("first"),
+        &[
+            (
+                "http_func.inc",
+                "include('misc.inc');\n\
+                 HTTP_PORT = 80;\n\
+                 function http_get_port(default) { return misc(x:default); }\n",
+            ),
+            ("misc.inc", "function misc(x) { return x; }\n"),
+        ],
+    );
+    write_files(
+        &dir.join("second"),
+        &[
+            ("misc.inc", "function shadowed() {}\n"),
+            ("other.inc", "global_var OTHER;\nfunction other() {}\n"),
+        ],
+    );
+
+    let mut includes = IncludeResolver::new();
+    includes.add_search_path(dir.join("first"));
+    includes.add_search_path(dir.join("second"));
+
+    let code = "include('http_func.inc');\n\
+                include('other.inc');\n\
+                include('http_func.inc');\n\
+                port = http_get_port(default:HTTP_PORT);\n\
+                other(x:OTHER);\n";
+    let stmts = Parser::new(code).parse().unwrap();
+    let (symbols, errors) = includes.resolve_includes(Path::new("plugin.nasl"), &stmts);
+    assert_eq!(errors, vec![]);
+
+    // Every file is loaded once, `misc.inc` from the first search path.
+    let names: Vec<_> = includes.files().iter().map(|f| f.name.as_str()).collect();
+    assert_eq!(names, ["http_func.inc", "misc.inc", "other.inc"]);
+    assert_eq!(
+        includes.file(symbols.function("misc").unwrap().file).path,
+        dir.join("first/misc.inc")
+    );
+    assert!(symbols.function("shadowed").is_none());
+
+    let (tree, errors) = resolve_with_includes(&stmts, &Builtins::nasl(), &symbols);
+    assert_eq!(errors, vec![]);
+    let call = "http_get_port";
+    let start = code.find(call).unwrap();
+    let binding = tree.binding(Span::new(start, start + call.len(), 4, 8));
+    match binding {
+        Some(Binding::Included(def)) => {
+            assert_eq!(def.kind, DefKind::Function);
+            assert_eq!(includes.file(def.file).name, "http_func.inc");
+        }
+        binding => panic!("{:?}", binding),
+    }
+    assert_eq!(
+        symbols.variable("OTHER").unwrap().kind,
+        DefKind::Declared(VarScope::Global)
+    );
+}
+
+#[test]
+fn errors() {
+    let dir = feed("include_errors");
+    write_files(
+        &dir,
+        &[
+            ("a.inc", "include('b.inc');\nfunction a() {}\n"),
+            ("b.inc", "include('c.inc');\ninclude('a.inc');\n"),
+            ("c.inc", "include('d.inc');\nfunction c() {}\n"),
+            ("d.inc", "x = ;\nfunction d() {}\n"),
+        ],
+    );
+    let mut includes = IncludeResolver::new();
+    includes.add_search_path(&dir);
+
+    let code = "include('missing.inc');\ninclude('a.inc');\nc();";
+    let stmts = Parser::new(code).parse().unwrap();
+    let (symbols, errors) = includes.resolve_includes(Path::new("plugin.nasl"), &stmts);
+    let errors: Vec<_> = errors
+        .iter()
+        .map(|e| {
+            (
+                e.path
+                    .as_ref()
+                    .unwrap()
+                    .file_name()
+                    .unwrap()
+                    .to_str()
+                    .unwrap(),
+                e.kind,
+                e.message.as_str(),
+            )
+        })
+        .collect();
+    assert_eq!(
+        errors,
+        [
+            (
+                "plugin.nasl",
+                DiagnosticKind::Include,
+                "cannot include `missing.inc`: not found in the search paths"
+            ),
+            (
+                "d.inc",
+                DiagnosticKind::Syntax,
+                "expected expression, found `;`"
+            ),
+            (
+                "b.inc",
+                DiagnosticKind::Include,
+                "include cycle a.inc -> b.inc -> a.inc"
+            ),
+        ]
+    );
+    // The functions of every file are there, even with the cycle and the
+    // syntax error.
+    for name in ["a", "c", "d"].iter() {
+        assert!(symbols.function(name).is_some(), "{}", name);
+    }
+}
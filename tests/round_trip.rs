@@ -0,0 +1,107 @@
+//! Round trips through the unparser.
+//!
+//! Every `tests/golden/*.nasl` file, and every `.nasl` and `.inc` file below
+//! the directory in `NASL_FEED` if it is set, is parsed, printed and parsed
+//! again. Both trees have to be the same, which is checked by comparing
+//! their S-expression dumps, and printing the second tree has to give the
+//! same source again. The same holds for the formatter. The syntax tree of
+//! a file has to give back its source and AST exactly. Files that do not
+//! parse in the first place are skipped.
+
+use nasl_transpiler::format::format;
+use nasl_transpiler::parser::Parser;
+use nasl_transpiler::sexp::to_sexp;
+use nasl_transpiler::unparse::to_nasl;
+use std::fs;
+use std::path::{Path, PathBuf};
+
+fn collect(dir: &Path, files: &mut Vec<PathBuf>) {
+    for entry in fs::read_dir(dir).unwrap() {
+        let path = entry.unwrap().path();
+        if path.is_dir() {
+            collect(&path, files);
+        } else if path
+            .extension()
+            .is_some_and(|ext| ext == "nasl" || ext == "inc")
+        {
+            files.push(path);
+        }
+    }
+}
+
+/// Checks a single file, returns a description of the problem if it does
+/// not survive the round trip.
+fn round_trip(code: &str) -> Result<(), String> {
+    let stmts = match Parser::new(code).parse() {
+        Ok(stmts) => stmts,
+        Err(_) => return Ok(()),
+    };
+    let (tree, _) = Parser::new(code).parse_cst();
+    if tree.text() != code {
+        return Err(format!("syntax tree is not lossless:\n{}", tree.dump()));
+    }
+    if tree.to_ast().as_ref() != Ok(&stmts) {
+        return Err(format!("syntax tree gives another AST:\n{}", tree.dump()));
+    }
+
+    let printed = to_nasl(&stmts);
+    let reparsed = Parser::new(&printed)
+        .parse()
+        .map_err(|errors| format!("printed code does not parse: {:?}\n{}", errors, printed))?;
+    if to_sexp(&reparsed) != to_sexp(&stmts) {
+        return Err(format!("printed code parses differently:\n{}", printed));
+    }
+    let reprinted = to_nasl(&reparsed);
+    if reprinted != printed {
+        return Err(format!(
+            "printing is not stable:\n{}\n---\n{}",
+            printed, reprinted
+        ));
+    }
+
+    let formatted = format(code).map_err(|errors| format!("{:?}", errors))?;
+    let reformatted = format(&formatted)
+        .map_err(|errors| format!("formatted code does not parse: {:?}\n{}", errors, formatted))?;
+    if to_sexp(&Parser::new(&formatted).parse().unwrap()) != to_sexp(&stmts) {
+        return Err(format!("formatted code parses differently:\n{}", formatted));
+    }
+    if reformatted != formatted {
+        return Err(format!(
+            "formatting is not stable:\n{}\n---\n{}",
+            formatted, reformatted
+        ));
+    }
+    Ok(())
+}
+
+#[test]
+fn round_trip_files() {
+    let mut files = Vec::new();
+    collect(
+        &Path::new(env!("CARGO_MANIFEST_DIR")).join("tests/golden"),
+        &mut files,
+    );
+    if let Some(feed) = std::env::var_os("NASL_FEED") {
+        collect(Path::new(&feed), &mut files);
+    }
+    files.sort();
+
+    let mut failures = Vec::new();
+    for file in &files {
+        // Feed files are not necessarily UTF-8.
+        let code = String::from_utf8_lossy(&fs::read(file).unwrap()).into_owned();
+        if let Err(e) = round_trip(&code) {
+            failures.push(format!("{}: {}", file.display(), e));
+        }
+    }
+
+    assert!(failures.is_empty(), "{}", failures.join("\n\n"));
+}
+
+#[test]
+fn mixed_arguments() {
+    let code = "f(port:80, 1, data:d, 2);\n";
+    assert_eq!(to_nasl(&Parser::new(code).parse().unwrap()), code);
+    assert_eq!(format(code).unwrap(), code);
+    round_trip(code).unwrap();
+}